@@ -71,8 +71,8 @@ fn bench_add_to_kdtree_with_1k_3d_points(b: &mut Bencher) {
     for _ in 0..len {
         points.push(rand_data());
     }
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
     b.iter(|| kdtree.add(&point.0, point.1).unwrap());
 }
@@ -86,8 +86,8 @@ fn bench_nearest_from_kdtree_with_1k_3d_points(b: &mut Bencher) {
     for _ in 0..len {
         points.push(rand_data());
     }
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
     b.iter(|| kdtree.nearest(&point.0, 1000, &squared_euclidean).unwrap());
 }
@@ -103,8 +103,8 @@ fn bench_nearest_from_kdtree_with_150k_3d_points_squared_euclidean(b: &mut Bench
         points.push(rand_sphere_data());
     }
 
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
 
     b.iter(|| kdtree.nearest(&point.0, 50000, &squared_euclidean).unwrap());
@@ -121,8 +121,8 @@ fn bench_nearest_with_1_from_kdtree_with_150k_3d_points_squared_euclidean(b: &mu
         points.push(rand_sphere_data());
     }
 
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
 
     b.iter(|| kdtree.nearest(&point.0, 1, &squared_euclidean).unwrap());
@@ -139,8 +139,8 @@ fn bench_nearest_one_from_kdtree_with_150k_3d_points_squared_euclidean(b: &mut B
         points.push(rand_sphere_data());
     }
 
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
 
     b.iter(|| kdtree.nearest_one(&point.0, &squared_euclidean).unwrap());
@@ -157,8 +157,8 @@ fn bench_best_n_within_150k_3d_squared_euclidean(b: &mut Bencher) {
         points.push(rand_sphere_data());
     }
 
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
 
     b.iter(|| kdtree.best_n_within(&point.0, 0.1, 500, &squared_euclidean).unwrap());
@@ -175,8 +175,8 @@ fn bench_nearest_from_kdtree_with_150k_3d_points_dot_product(b: &mut Bencher) {
         points.push(rand_sphere_data_f32());
     }
 
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
 
     b.iter(|| kdtree.nearest(&point.0, 50000, &dot_product).unwrap());
@@ -194,8 +194,8 @@ fn bench_nearest_from_kdtree_with_150k_3d_points_dot_product_sse(b: &mut Bencher
         points.push(rand_sphere_data_f32());
     }
 
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
 
     println!("calling nearest");
@@ -214,8 +214,8 @@ fn bench_nearest_from_kdtree_with_150k_3d_points_dot_product_sse_aligned(b: &mut
         points.push(rand_sphere_data_f32_qw());
     }
 
-    for i in 0..points.len() {
-        kdtree.add(&points[i].0, points[i].1).unwrap();
+    for (pos, data) in points.iter() {
+        kdtree.add(pos, *data).unwrap();
     }
 
     println!("calling nearest");
@@ -1,64 +1,18 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 
-use aligned::{Aligned, A16};
 use kiddo::distance::squared_euclidean;
 use kiddo::KdTree;
-use num_traits::{Float, FromPrimitive};
 use rand_distr::Distribution;
 use rand_distr::UnitSphere as SPHERE;
 
-use std::arch::x86_64::*;
-use std::collections::BinaryHeap;
-
-union SimdToArray {
-    array: [f32; 4],
-    simd: __m128,
-}
-//fn rand_data() -> ([f64; 3], f64) {
-//rand::random()
-//}
-
 fn rand_unit_sphere_point_f64() -> [f64; 3] {
     SPHERE.sample(&mut rand::thread_rng())
 }
 
-fn rand_unit_sphere_point_f32() -> [f32; 3] {
-    let sph64: [f64; 3] = SPHERE.sample(&mut rand::thread_rng());
-    let res: Aligned<A16, _> = Aligned([
-        f32::from_f64(sph64[0]).unwrap(),
-        f32::from_f64(sph64[1]).unwrap(),
-        f32::from_f64(sph64[2]).unwrap(),
-    ]);
-    *res
-}
-
-fn rand_unit_sphere_point_f32_qwalign() -> [f32; 4] {
-    let sph64: [f64; 3] = SPHERE.sample(&mut rand::thread_rng());
-    let res: Aligned<A16, _> = Aligned([
-        f32::from_f64(sph64[0]).unwrap(),
-        f32::from_f64(sph64[1]).unwrap(),
-        f32::from_f64(sph64[2]).unwrap(),
-        0f32,
-    ]);
-    *res
-}
-
-fn rand_data() -> ([f64; 3], f64) {
-    rand::random()
-}
-
 fn rand_sphere_data() -> ([f64; 3], usize) {
     (rand_unit_sphere_point_f64(), rand::random())
 }
 
-fn rand_sphere_data_f32() -> ([f32; 3], usize) {
-    (rand_unit_sphere_point_f32(), rand::random())
-}
-
-fn rand_sphere_data_f32_qw() -> ([f32; 4], usize) {
-    (rand_unit_sphere_point_f32_qwalign(), rand::random())
-}
-
 pub fn best_1_within_small_euclidean2(c: &mut Criterion) {
     let mut group = c.benchmark_group("best 1: within(0.01)");
 
@@ -72,8 +26,8 @@ pub fn best_1_within_small_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -100,8 +54,8 @@ pub fn best_1_within_medium_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -128,8 +82,8 @@ pub fn best_1_within_large_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -156,8 +110,8 @@ pub fn best_100_within_small_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -184,8 +138,8 @@ pub fn best_100_within_medium_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -212,8 +166,8 @@ pub fn best_100_within_large_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
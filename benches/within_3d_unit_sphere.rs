@@ -1,64 +1,18 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 
-use aligned::{Aligned, A16};
 use kiddo::distance::squared_euclidean;
 use kiddo::KdTree;
-use num_traits::FromPrimitive;
 use rand_distr::Distribution;
 use rand_distr::UnitSphere as SPHERE;
 
-use std::arch::x86_64::*;
-
-union SimdToArray {
-    array: [f32; 4],
-    simd: __m128,
-}
-
-//fn rand_data() -> ([f64; 3], f64) {
-//rand::random()
-//}
-
 fn rand_unit_sphere_point_f64() -> [f64; 3] {
     SPHERE.sample(&mut rand::thread_rng())
 }
 
-fn rand_unit_sphere_point_f32() -> [f32; 3] {
-    let sph64: [f64; 3] = SPHERE.sample(&mut rand::thread_rng());
-    let res: Aligned<A16, _> = Aligned([
-        f32::from_f64(sph64[0]).unwrap(),
-        f32::from_f64(sph64[1]).unwrap(),
-        f32::from_f64(sph64[2]).unwrap(),
-    ]);
-    *res
-}
-
-fn rand_unit_sphere_point_f32_qwalign() -> [f32; 4] {
-    let sph64: [f64; 3] = SPHERE.sample(&mut rand::thread_rng());
-    let res: Aligned<A16, _> = Aligned([
-        f32::from_f64(sph64[0]).unwrap(),
-        f32::from_f64(sph64[1]).unwrap(),
-        f32::from_f64(sph64[2]).unwrap(),
-        0f32,
-    ]);
-    *res
-}
-
-fn rand_data() -> ([f64; 3], f64) {
-    rand::random()
-}
-
 fn rand_sphere_data() -> ([f64; 3], usize) {
     (rand_unit_sphere_point_f64(), rand::random())
 }
 
-fn rand_sphere_data_f32() -> ([f32; 3], usize) {
-    (rand_unit_sphere_point_f32(), rand::random())
-}
-
-fn rand_sphere_data_f32_qw() -> ([f32; 4], usize) {
-    (rand_unit_sphere_point_f32_qwalign(), rand::random())
-}
-
 pub fn within_small_euclidean2(c: &mut Criterion) {
     let mut group = c.benchmark_group("within(0.01)");
 
@@ -72,8 +26,8 @@ pub fn within_small_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| black_box(kdtree.within(&point.0, 0.01, &squared_euclidean)).unwrap());
@@ -94,8 +48,8 @@ pub fn within_medium_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| black_box(kdtree.within(&point.0, 0.05, &squared_euclidean)).unwrap());
@@ -116,8 +70,8 @@ pub fn within_large_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| black_box(kdtree.within(&point.0, 0.25, &squared_euclidean)).unwrap());
@@ -138,8 +92,8 @@ pub fn within_unsorted_small_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -162,8 +116,8 @@ pub fn within_unsorted_medium_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -186,8 +140,8 @@ pub fn within_unsorted_large_euclidean2(c: &mut Criterion) {
             for _ in 0..size {
                 points.push(rand_sphere_data());
             }
-            for i in 0..points.len() {
-                kdtree.add(&points[i].0, points[i].1).unwrap();
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
             }
 
             b.iter(|| {
@@ -197,6 +151,30 @@ pub fn within_unsorted_large_euclidean2(c: &mut Criterion) {
     }
 }
 
+pub fn within_medium_euclidean2_searcher(c: &mut Criterion) {
+    let mut group = c.benchmark_group("within(0.05) with reused searcher");
+
+    for size in [100, 1_000, 10_000, 100_000, 1_000_000].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            let point = rand_sphere_data();
+
+            let mut points = vec![];
+            let mut kdtree = KdTree::with_per_node_capacity(16).unwrap();
+            for _ in 0..size {
+                points.push(rand_sphere_data());
+            }
+            for (pos, data) in points.iter() {
+                kdtree.add(pos, *data).unwrap();
+            }
+
+            let mut searcher = kdtree.searcher();
+            b.iter(|| {
+                black_box(searcher.within(&point.0, 0.05, &squared_euclidean).unwrap());
+            });
+        });
+    }
+}
+
 criterion_group!(
     benches,
     within_small_euclidean2,
@@ -204,6 +182,7 @@ criterion_group!(
     within_large_euclidean2,
     within_unsorted_small_euclidean2,
     within_unsorted_medium_euclidean2,
-    within_unsorted_large_euclidean2
+    within_unsorted_large_euclidean2,
+    within_medium_euclidean2_searcher
 );
 criterion_main!(benches);
@@ -0,0 +1,300 @@
+//! A tree that can be queried from many threads while another thread modifies it.
+//!
+//! Wrapping a [`KdTree`] in a `RwLock` makes every query wait while an element is
+//! being added, and every addition wait for all queries in flight. A
+//! [`ConcurrentKdTree`] instead publishes immutable snapshots of the tree. Queries
+//! run against the latest snapshot without holding any lock, and each modification
+//! publishes a new snapshot once it is complete.
+//!
+//! Snapshots are [`PersistentKdTree`]s, which share the nodes they have in common. A
+//! modification copies only the nodes on the path from the root to the leaf that it
+//! changes, and every other node stays shared with the snapshots that queries are still
+//! using. Each modification therefore copies at most one leaf's elements, however large
+//! the tree is and however many snapshots are held.
+
+use std::sync::{Mutex, RwLock};
+
+use num_traits::{Float, One, Signed, Zero};
+
+use crate::kiddo::{ErrorKind, KdTree};
+use crate::persistent::PersistentKdTree;
+
+/// A [`KdTree`] that one thread can modify while other threads query it.
+///
+/// Queries take a brief read lock to take the latest snapshot, then run without any lock
+/// held. Modifications are serialised with each other, but never wait for queries to
+/// finish. Holding on to a snapshot taken by [`snapshot`] does not make later
+/// modifications any more expensive, although the nodes that it shares with no newer
+/// snapshot are only freed once it is dropped.
+///
+/// [`snapshot`]: ConcurrentKdTree::snapshot
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use kiddo::ConcurrentKdTree;
+/// use kiddo::distance::squared_euclidean;
+///
+/// let tree: Arc<ConcurrentKdTree<f64, usize, 2>> = Arc::new(ConcurrentKdTree::new());
+/// tree.add(&[0.0, 0.0], 0)?;
+///
+/// let reader = {
+///     let tree = Arc::clone(&tree);
+///     thread::spawn(move || {
+///         for _ in 0..100 {
+///             let (_, nearest) = tree.nearest_one(&[0.1, 0.1], &squared_euclidean).unwrap();
+///             assert!(nearest < 100);
+///         }
+///     })
+/// };
+///
+/// for idx in 1..100 {
+///     tree.add(&[idx as f64, idx as f64], idx)?;
+/// }
+/// reader.join().unwrap();
+///
+/// assert_eq!(tree.size(), 100);
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+pub struct ConcurrentKdTree<A, T: PartialEq, const K: usize> {
+    published: RwLock<PersistentKdTree<A, T, K>>,
+    // The tree that modifications are applied to. Its nodes are shared with the published
+    // snapshot until a modification copies them.
+    writer: Mutex<PersistentKdTree<A, T, K>>,
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq + Clone, const K: usize> Default
+    for ConcurrentKdTree<A, T, K>
+{
+    fn default() -> Self {
+        ConcurrentKdTree::new()
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq + Clone, const K: usize> From<KdTree<A, T, K>>
+    for ConcurrentKdTree<A, T, K>
+{
+    fn from(tree: KdTree<A, T, K>) -> Self {
+        let tree = PersistentKdTree::from(tree);
+
+        ConcurrentKdTree {
+            published: RwLock::new(tree.snapshot()),
+            writer: Mutex::new(tree),
+        }
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq + Clone, const K: usize>
+    ConcurrentKdTree<A, T, K>
+{
+    /// Creates a new, empty `ConcurrentKdTree` with default capacity **per node** of 16.
+    /// To use a different capacity, or periodic boundary conditions, configure a
+    /// [`KdTree`] and convert it with `ConcurrentKdTree::from`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::ConcurrentKdTree;
+    ///
+    /// let tree: ConcurrentKdTree<f64, usize, 3> = ConcurrentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn new() -> Self {
+        ConcurrentKdTree::from(KdTree::new())
+    }
+
+    /// Returns the latest snapshot of the tree. The snapshot is unaffected by any later
+    /// modification, so any number of queries can be run against it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::ConcurrentKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let tree: ConcurrentKdTree<f64, usize, 3> = ConcurrentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// let snapshot = tree.snapshot();
+    /// tree.add(&[1.0, 2.0, 5.1], 101)?;
+    ///
+    /// assert_eq!(snapshot.size(), 1);
+    /// assert_eq!(*snapshot.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn snapshot(&self) -> PersistentKdTree<A, T, K> {
+        self.published.read().unwrap().snapshot()
+    }
+
+    /// Returns the number of elements in the latest snapshot of the tree
+    pub fn size(&self) -> usize {
+        self.published.read().unwrap().size()
+    }
+
+    /// Adds an element to the tree, as described for [`KdTree::add`]. The element is
+    /// visible to every query that starts after this returns.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::ConcurrentKdTree;
+    ///
+    /// let tree: ConcurrentKdTree<f64, usize, 3> = ConcurrentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[1.1, 2.1, 5.1], 101)?;
+    ///
+    /// assert_eq!(tree.size(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn add(&self, point: &[A; K], data: T) -> Result<(), ErrorKind> {
+        self.modify(|tree| tree.add(point, data))
+    }
+
+    /// Removes every element stored at `point` that is equal to `data`, as described for
+    /// [`KdTree::remove`]. Returns the number of elements removed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::ConcurrentKdTree;
+    ///
+    /// let tree: ConcurrentKdTree<f64, usize, 3> = ConcurrentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[1.1, 2.1, 5.1], 101)?;
+    ///
+    /// assert_eq!(tree.remove(&[1.0, 2.0, 5.0], &100)?, 1);
+    /// assert_eq!(tree.size(), 1);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn remove(&self, point: &[A; K], data: &T) -> Result<usize, ErrorKind> {
+        self.modify(|tree| tree.remove(point, data))
+    }
+
+    fn modify<R, M>(&self, modification: M) -> Result<R, ErrorKind>
+    where
+        M: FnOnce(&mut PersistentKdTree<A, T, K>) -> Result<R, ErrorKind>,
+    {
+        let mut tree = self.writer.lock().unwrap();
+
+        // On failure the tree is unchanged, and nothing needs publishing
+        let result = modification(&mut tree)?;
+        *self.published.write().unwrap() = tree.snapshot();
+
+        Ok(result)
+    }
+
+    /// Queries the latest snapshot of the tree to find the nearest `num` elements to
+    /// `point`, as described for [`KdTree::nearest`]. The elements are cloned, so that they
+    /// remain valid after the snapshot is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::ConcurrentKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let tree: ConcurrentKdTree<f64, usize, 3> = ConcurrentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let nearest = tree.nearest(&[1.0, 2.0, 5.1], 1, &squared_euclidean)?;
+    ///
+    /// assert_eq!(nearest.len(), 1);
+    /// assert_eq!(nearest[0].1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest<F>(
+        &self,
+        point: &[A; K],
+        num: usize,
+        distance: &F,
+    ) -> Result<Vec<(A, T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let snapshot = self.snapshot();
+        let nearest = snapshot.nearest(point, num, distance)?;
+
+        Ok(nearest
+            .into_iter()
+            .map(|(dist, item)| (dist, item.clone()))
+            .collect())
+    }
+
+    /// Queries the latest snapshot of the tree to find the nearest element to `point`, as
+    /// described for [`KdTree::nearest_one`]. The element is cloned, so that it remains
+    /// valid after the snapshot is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::ConcurrentKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let tree: ConcurrentKdTree<f64, usize, 3> = ConcurrentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let nearest = tree.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// assert_eq!(nearest.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_one<F>(&self, point: &[A; K], distance: &F) -> Result<(A, T), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let snapshot = self.snapshot();
+        let (dist, item) = snapshot.nearest_one(point, distance)?;
+
+        Ok((dist, item.clone()))
+    }
+
+    /// Queries the latest snapshot of the tree to find all elements within `radius` of
+    /// `point`, as described for [`KdTree::within`]. The elements are cloned, so that they
+    /// remain valid after the snapshot is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::ConcurrentKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let tree: ConcurrentKdTree<f64, usize, 3> = ConcurrentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let within = tree.within(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<Vec<(A, T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let snapshot = self.snapshot();
+        let within = snapshot.within(point, radius, distance)?;
+
+        Ok(within
+            .into_iter()
+            .map(|(dist, item)| (dist, item.clone()))
+            .collect())
+    }
+}
@@ -1,12 +1,13 @@
 #[cfg(feature = "serialize")]
 pub(crate) mod arrays {
+    use alloc::vec::Vec;
     use core::option::Option::None;
+    use core::{convert::TryInto, fmt, marker::PhantomData};
     use serde::{
         de::{SeqAccess, Visitor},
         ser::SerializeTuple,
         Deserialize, Deserializer, Serialize, Serializer,
     };
-    use std::{convert::TryInto, marker::PhantomData};
 
     pub fn serialize<S: Serializer, T: Serialize, const N: usize>(
         data: &[T; N],
@@ -27,8 +28,8 @@ pub(crate) mod arrays {
     {
         type Value = [T; N];
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str(&format!("an array of length {}", N))
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "an array of length {}", N)
         }
 
         #[inline]
@@ -61,13 +62,14 @@ pub(crate) mod arrays {
 
 #[cfg(feature = "serialize")]
 pub(crate) mod vec_arrays {
+    use alloc::vec::Vec;
     use core::option::Option::None;
+    use core::{convert::TryInto, fmt, marker::PhantomData};
     use serde::ser::SerializeSeq;
     use serde::{
         de::{SeqAccess, Visitor},
         Deserialize, Deserializer, Serialize, Serializer,
     };
-    use std::{convert::TryInto, marker::PhantomData};
 
     pub fn serialize<S: Serializer, T: Serialize, const N: usize>(
         data: &[[T; N]],
@@ -91,8 +93,8 @@ pub(crate) mod vec_arrays {
     {
         type Value = Vec<[T; N]>;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str(&format!("a vector of arrays of length {}", N))
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "a vector of arrays of length {}", N)
         }
 
         #[inline]
@@ -101,10 +103,10 @@ pub(crate) mod vec_arrays {
             A: SeqAccess<'de>,
         {
             // can be optimized using MaybeUninit
-            let mut result = if let Some(len) = seq.size_hint() {
-                Vec::with_capacity(len / N)
-            } else {
-                Vec::new()
+            let mut result = match seq.size_hint() {
+                // Arrays of length zero are written as no values at all
+                Some(len) if N > 0 => Vec::with_capacity(len / N),
+                _ => Vec::new(),
             };
 
             while let Some(val) = seq.next_element()? {
@@ -118,10 +120,11 @@ pub(crate) mod vec_arrays {
                     }
                 }
 
+                // Only fails if `N` is zero, in which case no values should have been written
                 let item_arr: [T; N] = match item.try_into() {
-                    Ok(arr) => Ok(arr),
-                    Err(_) => unreachable!(),
-                }?;
+                    Ok(arr) => arr,
+                    Err(_) => return Err(serde::de::Error::invalid_length(1, &self)),
+                };
 
                 result.push(item_arr);
             }
@@ -138,3 +141,264 @@ pub(crate) mod vec_arrays {
         deserializer.deserialize_seq(VecArrayVisitor::<T, N>(PhantomData))
     }
 }
+
+#[cfg(feature = "serialize")]
+mod kdtree {
+    use alloc::vec::Vec;
+    use core::{fmt, marker::PhantomData};
+    use num_traits::{Float, One, Signed, Zero};
+    use serde::de::{Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::vec_arrays;
+    use crate::kiddo::KdTree;
+    use crate::periodic::PeriodicBox;
+
+    // The version of the format written by `KdTree::serialize`. Increment it whenever
+    // the format changes, dispatching on the version in `TreeVisitor` and `read_map` for
+    // each older version that is still supported, so that trees written in a newer format
+    // are rejected rather than misread.
+    const FORMAT_VERSION: u32 = 1;
+
+    const FIELDS: &[&str] = &[
+        "version",
+        "dimensions",
+        "capacity",
+        "periodic",
+        "points",
+        "items",
+    ];
+
+    // A tree is written as a flat list of its elements, along with the parameters it was
+    // created with, rather than as its nodes. This keeps the format independent of how
+    // nodes are laid out, and means that reading a deep tree does not recurse. The tree
+    // is rebuilt from its elements when it is read.
+    #[derive(Serialize)]
+    #[serde(rename = "KdTree", bound = "A: Serialize, T: Serialize")]
+    struct FlatTreeRef<'a, A, T, const K: usize> {
+        version: u32,
+        dimensions: usize,
+        capacity: usize,
+        periodic: Option<&'a PeriodicBox<A, K>>,
+        #[serde(serialize_with = "vec_arrays::serialize")]
+        points: Vec<[A; K]>,
+        items: Vec<&'a T>,
+    }
+
+    // The fields of a tree written in the current format, after its version
+    struct FlatTree<A, T, const K: usize> {
+        dimensions: usize,
+        capacity: usize,
+        periodic: Option<PeriodicBox<A, K>>,
+        points: Vec<[A; K]>,
+        items: Vec<T>,
+    }
+
+    // The points of a tree, which are written as one flat sequence of coordinates
+    struct Points<A, const K: usize>(Vec<[A; K]>);
+
+    impl<'de, A: Deserialize<'de>, const K: usize> Deserialize<'de> for Points<A, K> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            vec_arrays::deserialize(deserializer).map(Points)
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(field_identifier, rename_all = "lowercase")]
+    enum Field {
+        Version,
+        Dimensions,
+        Capacity,
+        Periodic,
+        Points,
+        Items,
+        #[serde(other)]
+        Other,
+    }
+
+    impl<A, T, const K: usize> Serialize for KdTree<A, T, K>
+    where
+        A: Float + Zero + One + Signed + Serialize,
+        T: PartialEq + Serialize,
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let (points, items) = self.iter().map(|(point, item)| (*point, item)).unzip();
+
+            FlatTreeRef {
+                version: FORMAT_VERSION,
+                dimensions: K,
+                capacity: self.capacity(),
+                periodic: self.periodic(),
+                points,
+                items,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, A, T, const K: usize> Deserialize<'de> for KdTree<A, T, K>
+    where
+        A: Float + Zero + One + Signed + Deserialize<'de>,
+        T: PartialEq + Deserialize<'de>,
+    {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let flat =
+                deserializer.deserialize_struct("KdTree", FIELDS, TreeVisitor(PhantomData))?;
+
+            if flat.dimensions != K {
+                return Err(D::Error::custom(format_args!(
+                    "KdTree has {} dimensions, expected {}",
+                    flat.dimensions, K
+                )));
+            }
+            if flat.items.len() != flat.points.len() {
+                return Err(D::Error::invalid_length(
+                    flat.items.len(),
+                    &"one item for each point",
+                ));
+            }
+
+            KdTree::from_elements(flat.capacity, flat.periodic, flat.points, flat.items)
+                .map_err(D::Error::custom)
+        }
+    }
+
+    // Reads the version of a tree before its other fields, so that the rest of the tree
+    // can be read in the format that the version names
+    struct TreeVisitor<A, T, const K: usize>(PhantomData<(A, T)>);
+
+    impl<'de, A, T, const K: usize> Visitor<'de> for TreeVisitor<A, T, K>
+    where
+        A: Deserialize<'de>,
+        T: Deserialize<'de>,
+    {
+        type Value = FlatTree<A, T, K>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "a KdTree")
+        }
+
+        fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+            let version = seq
+                .next_element()?
+                .ok_or_else(|| S::Error::invalid_length(0, &self))?;
+
+            match version {
+                FORMAT_VERSION => read_seq(seq, &self),
+                version => Err(unsupported_version(version)),
+            }
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Self::Value, M::Error> {
+            read_map(map)
+        }
+    }
+
+    fn unsupported_version<E: Error>(version: u32) -> E {
+        E::custom(format_args!(
+            "unsupported KdTree format version {}, expected {}",
+            version, FORMAT_VERSION
+        ))
+    }
+
+    // Reads the fields that follow the version of a tree written as a sequence
+    fn read_seq<'de, A, T, S, const K: usize>(
+        mut seq: S,
+        expected: &dyn serde::de::Expected,
+    ) -> Result<FlatTree<A, T, K>, S::Error>
+    where
+        A: Deserialize<'de>,
+        T: Deserialize<'de>,
+        S: SeqAccess<'de>,
+    {
+        let dimensions = seq
+            .next_element()?
+            .ok_or_else(|| S::Error::invalid_length(1, expected))?;
+        let capacity = seq
+            .next_element()?
+            .ok_or_else(|| S::Error::invalid_length(2, expected))?;
+        let periodic = seq
+            .next_element()?
+            .ok_or_else(|| S::Error::invalid_length(3, expected))?;
+        let Points(points) = seq
+            .next_element()?
+            .ok_or_else(|| S::Error::invalid_length(4, expected))?;
+        let items = seq
+            .next_element()?
+            .ok_or_else(|| S::Error::invalid_length(5, expected))?;
+
+        Ok(FlatTree {
+            dimensions,
+            capacity,
+            periodic,
+            points,
+            items,
+        })
+    }
+
+    // Reads a tree written as a map. Its version is checked as soon as it is read, which
+    // is before any other field unless the format reorders the fields that `serialize`
+    // wrote. Unknown fields are skipped, so a tree written before the format was
+    // versioned, which holds its nodes instead, is rejected for having no version.
+    fn read_map<'de, A, T, M, const K: usize>(mut map: M) -> Result<FlatTree<A, T, K>, M::Error>
+    where
+        A: Deserialize<'de>,
+        T: Deserialize<'de>,
+        M: MapAccess<'de>,
+    {
+        let mut version = None;
+        let mut dimensions = None;
+        let mut capacity = None;
+        let mut periodic = None;
+        let mut points = None;
+        let mut items = None;
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                Field::Version if version.is_some() => {
+                    return Err(M::Error::duplicate_field("version"))
+                }
+                Field::Version => match map.next_value()? {
+                    FORMAT_VERSION => version = Some(FORMAT_VERSION),
+                    other => return Err(unsupported_version(other)),
+                },
+                Field::Dimensions if dimensions.is_some() => {
+                    return Err(M::Error::duplicate_field("dimensions"))
+                }
+                Field::Dimensions => dimensions = Some(map.next_value()?),
+                Field::Capacity if capacity.is_some() => {
+                    return Err(M::Error::duplicate_field("capacity"))
+                }
+                Field::Capacity => capacity = Some(map.next_value()?),
+                Field::Periodic if periodic.is_some() => {
+                    return Err(M::Error::duplicate_field("periodic"))
+                }
+                Field::Periodic => periodic = Some(map.next_value()?),
+                Field::Points if points.is_some() => {
+                    return Err(M::Error::duplicate_field("points"))
+                }
+                Field::Points => points = Some(map.next_value::<Points<A, K>>()?.0),
+                Field::Items if items.is_some() => return Err(M::Error::duplicate_field("items")),
+                Field::Items => items = Some(map.next_value()?),
+                Field::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        if version.is_none() {
+            return Err(M::Error::custom(
+                "KdTree has no format version; trees written by earlier releases of kiddo \
+                 are not supported",
+            ));
+        }
+
+        Ok(FlatTree {
+            dimensions: dimensions.ok_or_else(|| M::Error::missing_field("dimensions"))?,
+            capacity: capacity.ok_or_else(|| M::Error::missing_field("capacity"))?,
+            periodic: periodic.unwrap_or(None),
+            points: points.ok_or_else(|| M::Error::missing_field("points"))?,
+            items: items.ok_or_else(|| M::Error::missing_field("items"))?,
+        })
+    }
+}
@@ -4,10 +4,10 @@
 
 use num_traits::Float;
 
-#[cfg(any(target_arch = "x86_64"))]
-use std::arch::x86_64::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
 
-#[cfg(any(target_arch = "x86_64"))]
+#[cfg(target_arch = "x86_64")]
 union SimdToArray {
     array: [f32; 4],
     simd: __m128,
@@ -31,17 +31,21 @@ pub fn squared_euclidean<T: Float, const K: usize>(a: &[T; K], b: &[T; K]) -> T
     a.iter()
         .zip(b.iter())
         .map(|(x, y)| ((*x) - (*y)) * ((*x) - (*y)))
-        .fold(T::zero(), ::std::ops::Add::add)
+        .fold(T::zero(), core::ops::Add::add)
 }
 
 pub fn dot_product<const K: usize>(a: &[f32; K], b: &[f32; K]) -> f32 {
     a.iter()
         .zip(b.iter())
-        .map(|(x, y)| ((*x) * (*y)))
-        .fold(0f32, ::std::ops::Sub::sub)
+        .map(|(x, y)| (*x) * (*y))
+        .fold(0f32, core::ops::Sub::sub)
 }
 
-#[cfg(any(target_arch = "x86_64"))]
+/// # Safety
+///
+/// `a` and `b` must each point to at least four readable `f32`s, and the
+/// executing CPU must support SSE4.1.
+#[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "sse4.1")]
 pub unsafe fn dot_sse(a: *const f32, b: *const f32) -> f32 {
     let a_mm = _mm_loadu_ps(a);
@@ -53,7 +57,11 @@ pub unsafe fn dot_sse(a: *const f32, b: *const f32) -> f32 {
     res.array[0]
 }
 
-#[cfg(any(target_arch = "x86_64"))]
+/// # Safety
+///
+/// `a` and `b` must each point to at least four readable, 16-byte aligned
+/// `f32`s, and the executing CPU must support SSE4.1.
+#[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "sse4.1")]
 pub unsafe fn dot_sse_aligned(a: *const f32, b: *const f32) -> f32 {
     let a_mm = _mm_load_ps(a);
@@ -65,7 +73,7 @@ pub unsafe fn dot_sse_aligned(a: *const f32, b: *const f32) -> f32 {
     res.array[0]
 }
 
-#[cfg(any(target_arch = "x86_64"))]
+#[cfg(target_arch = "x86_64")]
 pub fn dot_product_sse<const K: usize>(a: &[f32; K], b: &[f32; K]) -> f32 {
     if K == 3 {
         dot_product_sse_3(&a[0..3], &a[0..3])
@@ -76,19 +84,19 @@ pub fn dot_product_sse<const K: usize>(a: &[f32; K], b: &[f32; K]) -> f32 {
     }
 }
 
-#[cfg(any(target_arch = "x86_64"))]
+#[cfg(target_arch = "x86_64")]
 pub fn dot_product_sse_3(a: &[f32], b: &[f32]) -> f32 {
     let ap = [a[0], a[1], a[2], 0f32].as_ptr();
     let bp = [b[0], b[1], b[2], 0f32].as_ptr();
     unsafe { dot_sse(ap, bp) }
 }
 
-#[cfg(any(target_arch = "x86_64"))]
+#[cfg(target_arch = "x86_64")]
 pub fn dot_product_sse_4(a: &[f32], b: &[f32]) -> f32 {
     unsafe { dot_sse(a.as_ptr(), b.as_ptr()) }
 }
 
-#[cfg(any(target_arch = "x86_64"))]
+#[cfg(target_arch = "x86_64")]
 pub fn dot_product_sse_aligned(a: &[f32; 4], b: &[f32; 4]) -> f32 {
     let ap = a.as_ptr();
     let bp = b.as_ptr();
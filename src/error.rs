@@ -0,0 +1,162 @@
+//! The errors returned by the trees in this crate.
+
+#[cfg(feature = "io")]
+use alloc::string::String;
+
+/// The errors that can be returned by a `KdTree`.
+///
+/// Coordinates and bounds are converted to `f64` so that they can be reported whatever
+/// the tree's coordinate type. As a non-finite coordinate may be `NaN`, match on the
+/// variant rather than comparing errors for equality where that matters.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A point lay outside the tree's periodic box along one of its periodic axes
+    PeriodicOutOfBounds {
+        operation: Operation,
+        axis: usize,
+        value: f64,
+        lower: f64,
+        upper: f64,
+    },
+    /// A periodic box had non-finite bounds along one of its periodic axes, or a lower
+    /// bound that was not below its upper bound
+    InvalidPeriodicBox { axis: usize, lower: f64, upper: f64 },
+    /// A point had a non-finite coordinate
+    NonFiniteCoordinate {
+        operation: Operation,
+        axis: usize,
+        value: f64,
+    },
+    /// A tree was created with a capacity of zero per node
+    ZeroCapacity,
+    /// The tree holds too few elements to satisfy a query
+    Empty,
+    /// A fixed-capacity tree or result vector had no room for another element
+    CapacityExceeded,
+    /// A file of points held `found` coordinates per point, rather than the tree's
+    /// `expected` number of dimensions
+    ShapeMismatch { expected: usize, found: usize },
+    /// A file of points could not be opened or read
+    #[cfg(feature = "io")]
+    Io(std::io::ErrorKind),
+    /// A file of points was malformed, or did not hold floating point coordinates. `row`
+    /// is the row at which the problem was found, where that is known: counting from zero
+    /// for NumPy and Parquet files, and the line counting from one for CSV files. `detail`
+    /// describes the problem.
+    #[cfg(feature = "io")]
+    InvalidFile { row: Option<u64>, detail: String },
+}
+
+/// The operation that was rejected with an `ErrorKind`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Adding an element to the tree
+    Add,
+    /// Removing an element from the tree
+    Remove,
+    /// Querying the tree
+    Query,
+}
+
+impl ErrorKind {
+    /// Returns the operation that was rejected, for errors caused by the point it was
+    /// given
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::{KdTree, Operation};
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new_periodic([10.0, 10.0, 10.0]);
+    ///
+    /// let err = tree.remove(&[1.0, 20.0, 5.0], &100).unwrap_err();
+    ///
+    /// assert_eq!(err.operation(), Some(Operation::Remove));
+    /// ```
+    pub fn operation(&self) -> Option<Operation> {
+        match self {
+            ErrorKind::PeriodicOutOfBounds { operation, .. }
+            | ErrorKind::NonFiniteCoordinate { operation, .. } => Some(*operation),
+            _ => None,
+        }
+    }
+
+    /// Returns the axis along which the offending point or periodic box was invalid
+    pub fn axis(&self) -> Option<usize> {
+        match self {
+            ErrorKind::PeriodicOutOfBounds { axis, .. }
+            | ErrorKind::InvalidPeriodicBox { axis, .. }
+            | ErrorKind::NonFiniteCoordinate { axis, .. } => Some(*axis),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ErrorKind {}
+
+impl core::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "KdTree error: ")?;
+        match *self {
+            ErrorKind::PeriodicOutOfBounds {
+                operation,
+                axis,
+                value,
+                lower,
+                upper,
+            } => write!(
+                f,
+                "{} rejected: coordinate {} on axis {} is outside the periodic bounds [{}, {}]",
+                operation, value, axis, lower, upper
+            ),
+            ErrorKind::InvalidPeriodicBox { axis, lower, upper } => write!(
+                f,
+                "periodic box has non-finite or empty bounds [{}, {}] on axis {}",
+                lower, upper, axis
+            ),
+            ErrorKind::NonFiniteCoordinate {
+                operation,
+                axis,
+                value,
+            } => write!(
+                f,
+                "{} rejected: non-finite coordinate {} on axis {}",
+                operation, value, axis
+            ),
+            ErrorKind::ZeroCapacity => write!(f, "zero capacity"),
+            ErrorKind::Empty => write!(f, "invalid operation on empty tree"),
+            ErrorKind::CapacityExceeded => write!(f, "fixed capacity exceeded"),
+            ErrorKind::ShapeMismatch { expected, found } => write!(
+                f,
+                "expected points with {} coordinates, found {}",
+                expected, found
+            ),
+            #[cfg(feature = "io")]
+            ErrorKind::Io(kind) => write!(f, "could not read point file: {}", kind),
+            #[cfg(feature = "io")]
+            ErrorKind::InvalidFile {
+                row: Some(row),
+                ref detail,
+            } => write!(f, "invalid point file at row {}: {}", row, detail),
+            #[cfg(feature = "io")]
+            ErrorKind::InvalidFile {
+                row: None,
+                ref detail,
+            } => write!(f, "invalid point file: {}", detail),
+        }
+    }
+}
+
+impl core::fmt::Display for Operation {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let name = match *self {
+            Operation::Add => "add",
+            Operation::Remove => "remove",
+            Operation::Query => "query",
+        };
+        write!(f, "{}", name)
+    }
+}
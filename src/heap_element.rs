@@ -1,5 +1,5 @@
+use core::cmp::Ordering;
 use num_traits::Float;
-use std::cmp::Ordering;
 
 pub struct HeapElement<A, T> {
     pub distance: A,
@@ -8,13 +8,15 @@ pub struct HeapElement<A, T> {
 
 impl<A: Float, T> Ord for HeapElement<A, T> {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+        self.distance
+            .partial_cmp(&other.distance)
+            .unwrap_or(Ordering::Equal)
     }
 }
 
 impl<A: Float, T> PartialOrd for HeapElement<A, T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.distance.partial_cmp(&other.distance)
+        Some(self.cmp(other))
     }
 }
 
@@ -41,8 +43,39 @@ impl<A: Float, T> PartialEq<A> for HeapElement<A, T> {
     }
 }
 
-impl<A: Float, T> Into<(A, T)> for HeapElement<A, T> {
-    fn into(self) -> (A, T) {
-        (self.distance, self.element)
+impl<A: Float, T> From<HeapElement<A, T>> for (A, T) {
+    fn from(val: HeapElement<A, T>) -> Self {
+        (val.distance, val.element)
+    }
+}
+
+impl<A: Float, P, T> From<HeapElement<A, (P, T)>> for (A, P, T) {
+    fn from(val: HeapElement<A, (P, T)>) -> Self {
+        (val.distance, val.element.0, val.element.1)
+    }
+}
+
+pub struct RankedElement<R, E> {
+    pub rank: R,
+    pub element: E,
+}
+
+impl<R: Ord, E> Ord for RankedElement<R, E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank.cmp(&other.rank)
+    }
+}
+
+impl<R: Ord, E> PartialOrd for RankedElement<R, E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R: Ord, E> Eq for RankedElement<R, E> {}
+
+impl<R: Ord, E> PartialEq for RankedElement<R, E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank
     }
 }
@@ -0,0 +1,474 @@
+//! Loading trees from files of points.
+//!
+//! With the `io` feature enabled, [`KdTree::from_npy`], [`KdTree::from_csv`] and
+//! [`KdTree::from_parquet`] read an N×K array of `f32` or `f64` coordinates from a NumPy
+//! `.npy` file, a CSV file or a Parquet file, and build a tree holding one element per
+//! row, whose item is the row's index. Trees are built in one pass from all of their
+//! points rather than by adding them one at a time, with the default capacity per node
+//! of 16. The `*_with_per_node_capacity` variants of each method take a capacity per
+//! node, and the `*_periodic_with_per_node_capacity` variants a periodic box as well.
+//!
+//! A file whose rows do not hold `K` coordinates is rejected with
+//! `ErrorKind::ShapeMismatch`. A file that cannot be opened or read is reported as
+//! `ErrorKind::Io`, and one that is malformed, does not hold floating point coordinates
+//! or holds a missing or non-finite coordinate as `ErrorKind::InvalidFile`, with the row
+//! at which the problem was found where that is known.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float32Type, Float64Type};
+use arrow_array::Array;
+use npyz::{NpyFile, Order};
+use num_traits::{Float, One, Signed, Zero};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use crate::kiddo::{ErrorKind, KdTree, DEFAULT_CAPACITY};
+use crate::periodic::PeriodicBox;
+
+impl<A: Float + Zero + One + Signed, const K: usize> KdTree<A, usize, K> {
+    /// Builds a tree from the rows of a two-dimensional NumPy `.npy` array of `f32` or
+    /// `f64` values, with `K` columns. A one-dimensional array is read as a single
+    /// column. Arrays stored in either C or Fortran order are supported.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let tree: KdTree<f64, usize, 3> = KdTree::from_npy("points.npy")?;
+    ///
+    /// let (_, row) = tree.nearest_one(&[1.0, 2.0, 5.0], &squared_euclidean)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_npy<P: AsRef<Path>>(path: P) -> Result<Self, ErrorKind> {
+        Self::from_points(read_npy(path)?, DEFAULT_CAPACITY, None)
+    }
+
+    /// Builds a tree from the rows of a NumPy `.npy` array, as described for
+    /// [`from_npy`](KdTree::from_npy), with a specific capacity **per node**.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    ///
+    /// let tree: KdTree<f64, usize, 3> =
+    ///     KdTree::from_npy_with_per_node_capacity("points.npy", 32)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_npy_with_per_node_capacity<P: AsRef<Path>>(
+        path: P,
+        capacity: usize,
+    ) -> Result<Self, ErrorKind> {
+        Self::from_points(read_npy(path)?, capacity, None)
+    }
+
+    /// Builds a tree from the rows of a NumPy `.npy` array, as described for
+    /// [`from_npy`](KdTree::from_npy), with a specific capacity **per node** and with
+    /// periodic boundary conditions, as described for
+    /// [`periodic_with_per_node_capacity`](KdTree::periodic_with_per_node_capacity).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    ///
+    /// let tree: KdTree<f64, usize, 3> = KdTree::from_npy_periodic_with_per_node_capacity(
+    ///     "points.npy",
+    ///     32,
+    ///     [10.0, 10.0, 10.0],
+    /// )?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_npy_periodic_with_per_node_capacity<P, B>(
+        path: P,
+        capacity: usize,
+        periodic: B,
+    ) -> Result<Self, ErrorKind>
+    where
+        P: AsRef<Path>,
+        B: Into<PeriodicBox<A, K>>,
+    {
+        Self::from_points(read_npy(path)?, capacity, Some(periodic.into()))
+    }
+
+    /// Builds a tree from the rows of a CSV file, each of which must hold `K` values.
+    /// If `has_headers` is `true`, the first row is skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let tree: KdTree<f64, usize, 3> = KdTree::from_csv("points.csv", true)?;
+    ///
+    /// let (_, row) = tree.nearest_one(&[1.0, 2.0, 5.0], &squared_euclidean)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_csv<P: AsRef<Path>>(path: P, has_headers: bool) -> Result<Self, ErrorKind> {
+        Self::from_points(read_csv(path, has_headers)?, DEFAULT_CAPACITY, None)
+    }
+
+    /// Builds a tree from the rows of a CSV file, as described for
+    /// [`from_csv`](KdTree::from_csv), with a specific capacity **per node**.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    ///
+    /// let tree: KdTree<f64, usize, 3> =
+    ///     KdTree::from_csv_with_per_node_capacity("points.csv", true, 32)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_csv_with_per_node_capacity<P: AsRef<Path>>(
+        path: P,
+        has_headers: bool,
+        capacity: usize,
+    ) -> Result<Self, ErrorKind> {
+        Self::from_points(read_csv(path, has_headers)?, capacity, None)
+    }
+
+    /// Builds a tree from the rows of a CSV file, as described for
+    /// [`from_csv`](KdTree::from_csv), with a specific capacity **per node** and with
+    /// periodic boundary conditions, as described for
+    /// [`periodic_with_per_node_capacity`](KdTree::periodic_with_per_node_capacity).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    ///
+    /// let tree: KdTree<f64, usize, 3> = KdTree::from_csv_periodic_with_per_node_capacity(
+    ///     "points.csv",
+    ///     true,
+    ///     32,
+    ///     [10.0, 10.0, 10.0],
+    /// )?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_csv_periodic_with_per_node_capacity<P, B>(
+        path: P,
+        has_headers: bool,
+        capacity: usize,
+        periodic: B,
+    ) -> Result<Self, ErrorKind>
+    where
+        P: AsRef<Path>,
+        B: Into<PeriodicBox<A, K>>,
+    {
+        Self::from_points(
+            read_csv(path, has_headers)?,
+            capacity,
+            Some(periodic.into()),
+        )
+    }
+
+    /// Builds a tree from the rows of a Parquet file with `K` columns, each of which must
+    /// hold `f32` or `f64` values. A null value is read as `NaN`, and so rejected as a
+    /// non-finite coordinate.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let tree: KdTree<f64, usize, 3> = KdTree::from_parquet("points.parquet")?;
+    ///
+    /// let (_, row) = tree.nearest_one(&[1.0, 2.0, 5.0], &squared_euclidean)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_parquet<P: AsRef<Path>>(path: P) -> Result<Self, ErrorKind> {
+        Self::from_points(read_parquet(path)?, DEFAULT_CAPACITY, None)
+    }
+
+    /// Builds a tree from the rows of a Parquet file, as described for
+    /// [`from_parquet`](KdTree::from_parquet), with a specific capacity **per node**.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    ///
+    /// let tree: KdTree<f64, usize, 3> =
+    ///     KdTree::from_parquet_with_per_node_capacity("points.parquet", 32)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_parquet_with_per_node_capacity<P: AsRef<Path>>(
+        path: P,
+        capacity: usize,
+    ) -> Result<Self, ErrorKind> {
+        Self::from_points(read_parquet(path)?, capacity, None)
+    }
+
+    /// Builds a tree from the rows of a Parquet file, as described for
+    /// [`from_parquet`](KdTree::from_parquet), with a specific capacity **per node** and
+    /// with periodic boundary conditions, as described for
+    /// [`periodic_with_per_node_capacity`](KdTree::periodic_with_per_node_capacity).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use kiddo::KdTree;
+    ///
+    /// let tree: KdTree<f64, usize, 3> = KdTree::from_parquet_periodic_with_per_node_capacity(
+    ///     "points.parquet",
+    ///     32,
+    ///     [10.0, 10.0, 10.0],
+    /// )?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn from_parquet_periodic_with_per_node_capacity<P, B>(
+        path: P,
+        capacity: usize,
+        periodic: B,
+    ) -> Result<Self, ErrorKind>
+    where
+        P: AsRef<Path>,
+        B: Into<PeriodicBox<A, K>>,
+    {
+        Self::from_points(read_parquet(path)?, capacity, Some(periodic.into()))
+    }
+
+    // Builds a tree whose items are the indices of `points`
+    fn from_points(
+        points: Vec<[A; K]>,
+        capacity: usize,
+        periodic: Option<PeriodicBox<A, K>>,
+    ) -> Result<Self, ErrorKind> {
+        let items = (0..points.len()).collect();
+        KdTree::from_elements(capacity, periodic, points, items)
+    }
+}
+
+fn read_npy<A: Float, const K: usize, P: AsRef<Path>>(path: P) -> Result<Vec<[A; K]>, ErrorKind> {
+    let file = File::open(path).map_err(|error| read_error(error, None))?;
+    let length = file
+        .metadata()
+        .map_err(|error| read_error(error, None))?
+        .len();
+    let npy = NpyFile::new(BufReader::new(file)).map_err(|error| read_error(error, None))?;
+
+    // Any dimensions after the first are flattened into the columns of each row
+    let rows = npy.shape().first().copied().unwrap_or(1);
+    let columns = npy
+        .shape()
+        .iter()
+        .skip(1)
+        .try_fold(1u64, |columns, &size| columns.checked_mul(size))
+        .and_then(|columns| usize::try_from(columns).ok())
+        .ok_or_else(|| ErrorKind::InvalidFile {
+            row: None,
+            detail: format!("array of shape {:?} is too large", npy.shape()),
+        })?;
+    if columns != K {
+        return Err(ErrorKind::ShapeMismatch {
+            expected: K,
+            found: columns,
+        });
+    }
+
+    let fortran = npy.order() == Order::Fortran;
+    match npy.try_data::<f64>() {
+        Ok(data) => read_rows(data, check_rows::<K>(rows, 8, length)?, fortran),
+        Err(npy) => {
+            let data = npy.data::<f32>().map_err(|error| read_error(error, None))?;
+            read_rows(data, check_rows::<K>(rows, 4, length)?, fortran)
+        }
+    }
+}
+
+// Checks that a file of `length` bytes is large enough to hold the `rows` rows of `K`
+// values of `size` bytes each that its header claims, before any room is made for them
+fn check_rows<const K: usize>(rows: u64, size: u64, length: u64) -> Result<usize, ErrorKind> {
+    rows.checked_mul(K as u64)
+        .and_then(|values| values.checked_mul(size))
+        .filter(|&bytes| bytes <= length)
+        .and_then(|_| usize::try_from(rows).ok())
+        .ok_or_else(|| ErrorKind::InvalidFile {
+            row: None,
+            detail: format!("header claims {} rows, more than the file holds", rows),
+        })
+}
+
+// Reads the values of an array with `rows` rows of `K` columns straight into its points,
+// without holding a second copy of the array
+fn read_rows<A, V, I, const K: usize>(
+    values: I,
+    rows: usize,
+    fortran: bool,
+) -> Result<Vec<[A; K]>, ErrorKind>
+where
+    A: Float,
+    V: Float,
+    I: Iterator<Item = io::Result<V>>,
+{
+    let mut points = vec![[A::zero(); K]; rows];
+    for (idx, value) in values.enumerate() {
+        let (row, column) = if fortran {
+            (idx % rows, idx / rows)
+        } else {
+            (idx / K, idx % K)
+        };
+        let value = value.map_err(|error| read_error(error, Some(row as u64)))?;
+        points[row][column] = finite(cast(value), Some(row as u64), column)?;
+    }
+
+    Ok(points)
+}
+
+fn read_csv<A: Float, const K: usize, P: AsRef<Path>>(
+    path: P,
+    has_headers: bool,
+) -> Result<Vec<[A; K]>, ErrorKind> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(has_headers)
+        .flexible(true)
+        .from_path(path)
+        .map_err(csv_error)?;
+
+    let mut points = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        if record.len() != K {
+            return Err(ErrorKind::ShapeMismatch {
+                expected: K,
+                found: record.len(),
+            });
+        }
+
+        let mut point = [A::zero(); K];
+        for (axis, (coordinate, field)) in point.iter_mut().zip(record.iter()).enumerate() {
+            let value: f64 = field.trim().parse().map_err(|_| ErrorKind::InvalidFile {
+                row: record.position().map(|position| position.line()),
+                detail: format!("invalid coordinate {:?}", field),
+            })?;
+            let row = record.position().map(|position| position.line());
+            *coordinate = finite(cast(value), row, axis)?;
+        }
+        points.push(point);
+    }
+
+    Ok(points)
+}
+
+fn read_parquet<A: Float, const K: usize, P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<[A; K]>, ErrorKind> {
+    let file = File::open(path).map_err(|error| read_error(error, None))?;
+    let builder =
+        ParquetRecordBatchReaderBuilder::try_new(file).map_err(|error| read_error(error, None))?;
+
+    let columns = builder.schema().fields().len();
+    if columns != K {
+        return Err(ErrorKind::ShapeMismatch {
+            expected: K,
+            found: columns,
+        });
+    }
+    let reader = builder.build().map_err(|error| read_error(error, None))?;
+
+    let mut points = Vec::new();
+    for batch in reader {
+        let start = points.len();
+        let batch = batch.map_err(|error| read_error(error, Some(start as u64)))?;
+        points.resize(start + batch.num_rows(), [A::zero(); K]);
+
+        for (axis, column) in batch.columns().iter().enumerate() {
+            let rows = &mut points[start..];
+            if let Some(values) = column.as_primitive_opt::<Float64Type>() {
+                for (row, (point, value)) in rows.iter_mut().zip(values.iter()).enumerate() {
+                    point[axis] = present(value, (start + row) as u64, axis)?;
+                }
+            } else if let Some(values) = column.as_primitive_opt::<Float32Type>() {
+                for (row, (point, value)) in rows.iter_mut().zip(values.iter()).enumerate() {
+                    point[axis] = present(value, (start + row) as u64, axis)?;
+                }
+            } else {
+                return Err(ErrorKind::InvalidFile {
+                    row: None,
+                    detail: format!(
+                        "column {} holds {} values, rather than Float32 or Float64",
+                        axis,
+                        column.data_type()
+                    ),
+                });
+            }
+        }
+    }
+
+    Ok(points)
+}
+
+// Converts a coordinate read from a file to the tree's coordinate type. Values that
+// cannot be represented become `NaN`, and so are rejected as non-finite coordinates.
+fn cast<A: Float, V: Float>(value: V) -> A {
+    A::from(value).unwrap_or_else(A::nan)
+}
+
+// Rejects a non-finite coordinate read from a file, reporting the row it was found at, as
+// a tree built from the file would only reject it without saying where it came from
+fn finite<A: Float>(value: A, row: Option<u64>, axis: usize) -> Result<A, ErrorKind> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(ErrorKind::InvalidFile {
+            row,
+            detail: format!(
+                "non-finite coordinate {} on axis {}",
+                value.to_f64().unwrap_or(f64::NAN),
+                axis
+            ),
+        })
+    }
+}
+
+// As `finite`, for a coordinate read from a Parquet column, which may be missing
+fn present<A: Float, V: Float>(value: Option<V>, row: u64, axis: usize) -> Result<A, ErrorKind> {
+    match value {
+        Some(value) => finite(cast(value), Some(row), axis),
+        None => Err(ErrorKind::InvalidFile {
+            row: Some(row),
+            detail: format!("missing coordinate on axis {}", axis),
+        }),
+    }
+}
+
+// Reports an error raised while reading a file as `ErrorKind::Io` if it was caused by an
+// I/O error, unless that only means the file's contents were malformed or cut short, and
+// otherwise as `ErrorKind::InvalidFile` at `row`
+fn read_error<E: Error + 'static>(error: E, row: Option<u64>) -> ErrorKind {
+    let mut cause: Option<&(dyn Error + 'static)> = Some(&error);
+    while let Some(inner) = cause {
+        if let Some(kind) = inner.downcast_ref::<io::Error>().map(io::Error::kind) {
+            if kind != io::ErrorKind::InvalidData && kind != io::ErrorKind::UnexpectedEof {
+                return ErrorKind::Io(kind);
+            }
+            break;
+        }
+        cause = inner.source();
+    }
+
+    ErrorKind::InvalidFile {
+        row,
+        detail: error.to_string(),
+    }
+}
+
+// As `read_error`, for CSV errors, which do not expose their causes through `source()`
+fn csv_error(error: csv::Error) -> ErrorKind {
+    let row = error.position().map(|position| position.line());
+    let detail = error.to_string();
+    match error.into_kind() {
+        csv::ErrorKind::Io(error) => read_error(error, row),
+        _ => ErrorKind::InvalidFile { row, detail },
+    }
+}
@@ -1,19 +1,37 @@
-use std::collections::BinaryHeap;
+use alloc::boxed::Box;
+use alloc::collections::BinaryHeap;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt;
+use core::iter::Zip;
+use core::ops::Deref;
+use core::slice;
 
 use num_traits::{Float, One, Zero};
-
-#[cfg(feature = "serialize")]
-use crate::custom_serde::*;
-use crate::heap_element::HeapElement;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+pub use crate::error::{ErrorKind, Operation};
+use crate::heap_element::{HeapElement, RankedElement};
+use crate::periodic::PeriodicBox;
+use crate::searcher::Searcher;
+use crate::stats::{QueryStats, TreeStats};
 use crate::util;
+use crate::util::Metric;
 use num_traits::Signed;
 
+/// The capacity **per node** of trees created without one being given, as by
+/// `KdTree::new()`
+pub const DEFAULT_CAPACITY: usize = 16;
+
 trait Stack<T>
 where
     T: Ord,
 {
     fn stack_push(&mut self, _: T);
-    fn stack_pop(&mut self) -> Option<T>;
 }
 
 impl<T> Stack<T> for Vec<T>
@@ -24,10 +42,6 @@ where
     fn stack_push(&mut self, element: T) {
         Vec::<T>::push(self, element)
     }
-    #[inline(always)]
-    fn stack_pop(&mut self) -> Option<T> {
-        Vec::<T>::pop(self)
-    }
 }
 
 impl<T> Stack<T> for BinaryHeap<T>
@@ -38,51 +52,163 @@ where
     fn stack_push(&mut self, element: T) {
         BinaryHeap::<T>::push(self, element)
     }
-    #[inline(always)]
-    fn stack_pop(&mut self) -> Option<T> {
-        BinaryHeap::<T>::pop(self)
-    }
 }
 
-#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug)]
-pub struct KdTree<A, T: std::cmp::PartialEq, const K: usize> {
-    size: usize,
-
-    #[cfg_attr(feature = "serialize", serde(with = "arrays"))]
-    min_bounds: [A; K],
-    #[cfg_attr(feature = "serialize", serde(with = "arrays"))]
-    max_bounds: [A; K],
-    content: Node<A, T, K>,
-    periodic: Option<[A; K]>,
+pub struct KdTree<A, T: PartialEq, const K: usize, O: Ownership = Owned> {
+    pub(crate) size: usize,
+
+    pub(crate) min_bounds: [A; K],
+    pub(crate) max_bounds: [A; K],
+    pub(crate) content: Node<A, T, K, O>,
+    pub(crate) periodic: Option<PeriodicBox<A, K>>,
 }
 
-#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-#[derive(Clone, Debug)]
-pub enum Node<A, T: std::cmp::PartialEq, const K: usize> {
+pub enum Node<A, T: PartialEq, const K: usize, O: Ownership = Owned> {
     Stem {
-        left: Box<KdTree<A, T, K>>,
-        right: Box<KdTree<A, T, K>>,
+        left: O::Child<KdTree<A, T, K, O>>,
+        right: O::Child<KdTree<A, T, K, O>>,
         split_value: A,
         split_dimension: u8,
     },
     Leaf {
-        #[cfg_attr(feature = "serialize", serde(with = "vec_arrays"))]
         points: Vec<[A; K]>,
         bucket: Vec<T>,
         capacity: usize,
     },
 }
 
-#[derive(Debug, PartialEq)]
-pub enum ErrorKind {
-    PeriodicOutOfBounds,
-    NonFiniteCoordinate,
-    ZeroCapacity,
-    Empty,
+/// How a stem of a [`KdTree`] holds its two children.
+///
+/// A `KdTree` owns its children outright, through a `Box`. A
+/// [`PersistentKdTree`](crate::PersistentKdTree) is built from the same nodes, but holds
+/// each child through an `Arc`, so that copies of the tree can share the nodes that they
+/// have in common.
+pub trait Ownership: Copy + fmt::Debug + private::Sealed {
+    /// The pointer through which a stem holds each of its children
+    type Child<N>: Deref<Target = N>;
+
+    /// Moves `node` behind a new pointer
+    fn new_child<N>(node: N) -> Self::Child<N>;
+
+    /// Copies `child`, which for a shared child only copies the pointer
+    fn clone_child<N: Clone>(child: &Self::Child<N>) -> Self::Child<N>;
+}
+
+/// The children of a [`KdTree`]'s stems are owned by the stem.
+#[derive(Clone, Copy, Debug)]
+pub struct Owned;
+
+impl Ownership for Owned {
+    type Child<N> = Box<N>;
+
+    fn new_child<N>(node: N) -> Box<N> {
+        Box::new(node)
+    }
+
+    fn clone_child<N: Clone>(child: &Box<N>) -> Box<N> {
+        child.clone()
+    }
+}
+
+/// The children of a [`PersistentKdTree`](crate::PersistentKdTree)'s stems are shared
+/// with any copies of the tree.
+#[cfg(target_has_atomic = "ptr")]
+#[derive(Clone, Copy, Debug)]
+pub struct Shared;
+
+#[cfg(target_has_atomic = "ptr")]
+impl Ownership for Shared {
+    type Child<N> = Arc<N>;
+
+    fn new_child<N>(node: N) -> Arc<N> {
+        Arc::new(node)
+    }
+
+    fn clone_child<N: Clone>(child: &Arc<N>) -> Arc<N> {
+        Arc::clone(child)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Owned {}
+    #[cfg(target_has_atomic = "ptr")]
+    impl Sealed for super::Shared {}
+}
+
+impl<A: Clone, T: Clone + PartialEq, const K: usize, O: Ownership> Clone for Node<A, T, K, O> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Stem {
+                left,
+                right,
+                split_value,
+                split_dimension,
+            } => Node::Stem {
+                left: O::clone_child(left),
+                right: O::clone_child(right),
+                split_value: split_value.clone(),
+                split_dimension: *split_dimension,
+            },
+            Node::Leaf {
+                points,
+                bucket,
+                capacity,
+            } => Node::Leaf {
+                points: points.clone(),
+                bucket: bucket.clone(),
+                capacity: *capacity,
+            },
+        }
+    }
+}
+
+impl<A: fmt::Debug, T: fmt::Debug + PartialEq, const K: usize, O: Ownership> fmt::Debug
+    for Node<A, T, K, O>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Stem {
+                left,
+                right,
+                split_value,
+                split_dimension,
+            } => f
+                .debug_struct("Stem")
+                .field("left", &**left)
+                .field("right", &**right)
+                .field("split_value", split_value)
+                .field("split_dimension", split_dimension)
+                .finish(),
+            Node::Leaf {
+                points,
+                bucket,
+                capacity,
+            } => f
+                .debug_struct("Leaf")
+                .field("points", points)
+                .field("bucket", bucket)
+                .field("capacity", capacity)
+                .finish(),
+        }
+    }
+}
+
+/// A query result that includes the stored point: the distance to the element,
+/// the coordinates at which it is stored, and the element itself.
+pub type PointResult<'a, A, T, const K: usize> = (A, &'a [A; K], &'a T);
+
+pub(crate) type Candidate<'a, A, T, const K: usize> = HeapElement<A, (&'a [A; K], &'a T)>;
+
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize> Default for KdTree<A, T, K> {
+    fn default() -> Self {
+        KdTree::new()
+    }
 }
 
-impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdTree<A, T, K> {
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize> KdTree<A, T, K> {
     /// Creates a new KdTree with default capacity **per node** of 16.
     ///
     /// # Examples
@@ -96,10 +222,17 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
     pub fn new() -> Self {
-        KdTree::with_per_node_capacity(16).unwrap()
+        KdTree::with_per_node_capacity(DEFAULT_CAPACITY).unwrap()
     }
 
     /// Creates a new KdTree with default capacity **per node** of 16, with periodic boundary conditions.
+    /// `periodic` is either a `PeriodicBox`, or an array of box lengths for a box with its
+    /// lower corner at the origin.
+    ///
+    /// Every query on the tree measures distances through the box, so there is no need to
+    /// call the `*_periodic` query methods, which override the box for a single query.
+    ///
+    /// Panics if the box is invalid.
     ///
     /// # Examples
     ///
@@ -111,8 +244,8 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn new_periodic(periodic: [A; K]) -> Self {
-        KdTree::periodic_with_per_node_capacity(16, periodic).unwrap()
+    pub fn new_periodic<P: Into<PeriodicBox<A, K>>>(periodic: P) -> Self {
+        KdTree::periodic_with_per_node_capacity(DEFAULT_CAPACITY, periodic).unwrap()
     }
 
     /// Creates a new KdTree with a specific capacity **per node**. You may wish to
@@ -130,28 +263,17 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
     pub fn with_per_node_capacity(capacity: usize) -> Result<Self, ErrorKind> {
-        if capacity == 0 {
-            return Err(ErrorKind::ZeroCapacity);
-        }
-
-        Ok(KdTree {
-            size: 0,
-            min_bounds: [A::infinity(); K],
-            max_bounds: [A::neg_infinity(); K],
-            content: Node::Leaf {
-                points: Vec::with_capacity(capacity),
-                bucket: Vec::with_capacity(capacity),
-                capacity,
-            },
-            periodic: None,
-        })
+        Self::empty(capacity, None)
     }
 
-    /// Creates a new KdTree with a specific capacity **per node**, and wih perodic
+    /// Creates a new KdTree with a specific capacity **per node**, and with periodic
     /// boundary conditions. You may wish to experiment by tuning this value to 
     /// best suit your workload via benchmarking: values between 10 and 40 often 
     /// work best.
     ///
+    /// Returns `ErrorKind::InvalidPeriodicBox` if the box has non-finite bounds, or if its
+    /// upper bound is not above its lower bound along every periodic axis.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -163,10 +285,39 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn periodic_with_per_node_capacity(capacity: usize, periodic: [A; K]) -> Result<Self, ErrorKind> {
+    pub fn periodic_with_per_node_capacity<P: Into<PeriodicBox<A, K>>>(
+        capacity: usize,
+        periodic: P,
+    ) -> Result<Self, ErrorKind> {
+        Self::empty(capacity, Some(periodic.into()))
+    }
+
+    /// Creates a new KdTree with a specific capacity **per node**.
+    ///
+    #[deprecated(
+        since = "0.1.8",
+        note = "with_capacity has a misleading name. Users should instead use with_per_node_capacity. with_capacity will be removed in a future release"
+    )]
+    pub fn with_capacity(capacity: usize) -> Result<Self, ErrorKind> {
+        Self::with_per_node_capacity(capacity)
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize, O: Ownership>
+    KdTree<A, T, K, O>
+{
+    // Creates an empty tree, checking the capacity and periodic box in the same way as
+    // the public constructors
+    pub(crate) fn empty(
+        capacity: usize,
+        periodic: Option<PeriodicBox<A, K>>,
+    ) -> Result<Self, ErrorKind> {
         if capacity == 0 {
             return Err(ErrorKind::ZeroCapacity);
         }
+        if let Some(periodic) = &periodic {
+            periodic.validate()?;
+        }
 
         Ok(KdTree {
             size: 0,
@@ -177,17 +328,10 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
                 bucket: Vec::with_capacity(capacity),
                 capacity,
             },
-            periodic: Some(periodic),
+            periodic,
         })
     }
 
-    /// Creates a new KdTree with a specific capacity **per node**.
-    ///
-    #[deprecated(since = "0.1.8", note = "with_capacity has a misleading name. Users should instead use with_per_node_capacity. with_capacity will be removed in a future release")]
-    pub fn with_capacity(capacity: usize) -> Result<Self, ErrorKind> {
-        Self::with_per_node_capacity(capacity)
-    }
-
     /// Returns the current number of elements stored in the tree
     ///
     /// # Examples
@@ -207,6 +351,30 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
         self.size
     }
 
+    /// Returns the periodic box of the tree, if it was created with periodic boundary conditions
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    ///
+    /// let tree: KdTree<f64, usize, 3> = KdTree::new_periodic([5.0, 5.0, 5.0]);
+    ///
+    /// assert_eq!(tree.periodic().unwrap().upper, [5.0, 5.0, 5.0]);
+    /// ```
+    pub fn periodic(&self) -> Option<&PeriodicBox<A, K>> {
+        self.periodic.as_ref()
+    }
+
+    // Pairs `distance` with the tree's periodic box, so that queries measure distances
+    // through the box's boundaries
+    pub(crate) fn metric<'a, F>(&self, distance: &'a F) -> Metric<'a, A, F, K>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        Metric::new(distance, self.periodic)
+    }
+
     /// Returns true if the node is a leaf node
     ///
     /// # Examples
@@ -236,529 +404,154 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
         }
     }
 
-    /// Queries the tree to find the nearest `num` elements to `point`, using the specified
-    /// distance metric function.
+    /// Returns the lower corner of the bounding box of the elements beneath this node
+    pub fn min_bounds(&self) -> &[A; K] {
+        &self.min_bounds
+    }
+
+    /// Returns the upper corner of the bounding box of the elements beneath this node
+    pub fn max_bounds(&self) -> &[A; K] {
+        &self.max_bounds
+    }
+
+    /// Returns the content of this node: either a stem, holding the two child nodes and
+    /// the plane that separates them, or a leaf, holding the elements themselves
     ///
     /// # Examples
     ///
     /// ```rust
     /// use kiddo::KdTree;
-    /// use kiddo::distance::squared_euclidean;
+    /// use kiddo::kiddo::Node;
     ///
-    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::with_capacity(1)?;
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     ///
-    /// let nearest = tree.nearest(&[1.0, 2.0, 5.1], 1, &squared_euclidean)?;
-    ///
-    /// assert_eq!(nearest.len(), 1);
-    /// assert!((nearest[0].0 - 0.01f64).abs() < f64::EPSILON);
-    /// assert_eq!(*nearest[0].1, 100);
+    /// match tree.node() {
+    ///     Node::Stem { left, right, .. } => assert_eq!(left.size() + right.size(), 2),
+    ///     Node::Leaf { .. } => unreachable!(),
+    /// }
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn nearest<F>(
-        &self,
-        point: &[A; K],
-        num: usize,
-        distance: &F,
-    ) -> Result<Vec<(A, &T)>, ErrorKind>
-    where
-        F: Fn(&[A; K], &[A; K]) -> A,
-    {
-        self.check_point(point)?;
-
-        let num = std::cmp::min(num, self.size);
-        if num == 0 {
-            return Ok(vec![]);
-        }
-
-        let mut pending = BinaryHeap::new();
-        let mut evaluated = BinaryHeap::<HeapElement<A, &T>>::new();
-
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
-
-        while !pending.is_empty()
-            && (evaluated.len() < num
-                || (-pending.peek().unwrap().distance <= evaluated.peek().unwrap().distance))
-        {
-            self.nearest_step(
-                point,
-                num,
-                A::infinity(),
-                distance,
-                &mut pending,
-                &mut evaluated,
-            );
-        }
-
-        Ok(evaluated
-            .into_sorted_vec()
-            .into_iter()
-            .take(num)
-            .map(Into::into)
-            .collect())
+    pub fn node(&self) -> &Node<A, T, K, O> {
+        &self.content
     }
 
-    /// Queries the tree to find the nearest `num` elements to `point`, using the specified
-    /// distance metric function. Obeys periodic boundary conditions.
+    /// Calls `visitor` with every node of the tree and its depth, where the root is at
+    /// depth 0. Nodes are visited depth-first, each stem before its left and then its
+    /// right child.
     ///
     /// # Examples
     ///
     /// ```rust
     /// use kiddo::KdTree;
-    /// use kiddo::distance::squared_euclidean;
     ///
-    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
-    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::with_capacity(1)?;
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     ///
-    /// let nearest = tree.nearest_periodic(&[1.0, 2.0, 5.1], 1, &squared_euclidean, &PERIODIC)?;
+    /// let mut leaf_depths = vec![];
+    /// tree.visit(|node, depth| {
+    ///     if node.is_leaf() {
+    ///         leaf_depths.push(depth);
+    ///     }
+    /// });
     ///
-    /// assert_eq!(nearest.len(), 1);
-    /// assert!((nearest[0].0 - 0.01f64).abs() < f64::EPSILON);
-    /// assert_eq!(*nearest[0].1, 100);
+    /// assert_eq!(leaf_depths, vec![1, 1]);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn nearest_periodic<F>(
-        &self,
-        point: &[A; K],
-        num: usize,
-        distance: &F,
-        periodic: &[A; K],
-    ) -> Result<Vec<(A, &T)>, ErrorKind>
+    pub fn visit<V>(&self, mut visitor: V)
     where
-        F: Fn(&[A; K], &[A; K]) -> A,
+        V: FnMut(&Self, usize),
     {
-        self.check_point(point)?;
-
-        let num = std::cmp::min(num, self.size);
-        if num == 0 {
-            return Ok(vec![]);
-        }
-
-        let mut pending = BinaryHeap::new();
-        let mut evaluated = BinaryHeap::<HeapElement<A, &T>>::new();
-
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
-
-        while !pending.is_empty()
-            && (evaluated.len() < num
-                || (-pending.peek().unwrap().distance <= evaluated.peek().unwrap().distance))
-        {
-            self.nearest_step(
-                point,
-                num,
-                A::infinity(),
-                distance,
-                &mut pending,
-                &mut evaluated,
-            );
-        }
-
-        // Find largest distance for canonical image
-        let largest_distance: A = evaluated
-            .iter()
-            .fold(A::zero(), |acc, x| acc.max(x.distance));
-        
-
-        // Find closest dist2 to every side
-        let mut closest_side_dist2: [A; K] = [A::zero(); K];
-        for side in 0..K {
-
-            // Do a single index here. This is equal to distance to lower side
-            let query_component: A = point[side];
+        let mut stack = vec![(self, 0)];
 
-            // Get distance to upper half
-            let upper = periodic[side] - query_component;
+        while let Some((node, depth)) = stack.pop() {
+            visitor(node, depth);
 
-            // !negative includes zero
-            debug_assert!(!upper.is_negative()); 
-            debug_assert!(!query_component.is_negative());
-
-            // Choose lesser of two and then square
-            closest_side_dist2[side] = upper.min(query_component).powi(2);
-        }
-
-        // Find which images we need to check.
-        // Initialize vector with canonical image (which we will remove later)
-        let mut images_to_check = Vec::with_capacity(2_usize.pow(K as u32)-1);
-        for image in 1..2_usize.pow(K as u32) {
-            
-            // Closest image in the form of bool array
-            let closest_image = (0..K)
-                .map(|idx| ((image / 2_usize.pow(idx as u32)) % 2) == 1);
-
-            // Find distance to corresponding side, edge, vertex or other higher dimensional equivalent
-            let dist_to_side_edge_or_other: A = closest_image
-                .clone()
-                .enumerate()
-                .flat_map(|(side, flag)| if flag {
-                    
-                    // Get minimum of dist2 to lower and upper side
-                    Some(closest_side_dist2[side])
-                } else { None })
-                .fold(A::zero(), |acc, x| acc + x);
-
-            if dist_to_side_edge_or_other < largest_distance {
-
-                let mut image_to_check = point.clone();
-                
-                for (idx, flag) in closest_image.enumerate() {
-
-                    // If moving image along this dimension
-                    if flag {
-                        // Do a single index here. This is equal to distance to lower side
-                        let query_component: A = point[idx];
-                        // Single index here as well
-                        let periodic_component = periodic[idx];
-
-                        if query_component < periodic_component / A::from(2_u8).unwrap() {
-                            // Add if in lower half of box
-                            image_to_check[idx] = query_component + periodic_component;
-                        } else {
-                            // Subtract if in upper half of box
-                            image_to_check[idx] = query_component - periodic_component;
-                        }
-                        
-                    }
-                }
-
-                images_to_check.push(image_to_check);
-            }
-        }
-
-        // Then check all images
-        for image in &images_to_check {
-    
-            let mut image_pending = BinaryHeap::new();
-            let mut image_evaluated = BinaryHeap::<HeapElement<A, &T>>::new();
-    
-            image_pending.push(HeapElement {
-                distance: A::zero(),
-                element: self,
-            });
-    
-            while !image_pending.is_empty()
-                && (image_evaluated.len() < num
-                    || (-image_pending.peek().unwrap().distance <= image_evaluated.peek().unwrap().distance))
-            {
-                self.nearest_step(
-                    image,
-                    num,
-                    A::infinity(),
-                    distance,
-                    &mut image_pending,
-                    &mut image_evaluated,
-                );
+            if let Node::Stem { left, right, .. } = &node.content {
+                stack.push((right, depth + 1));
+                stack.push((left, depth + 1));
             }
-
-            evaluated.append(&mut image_evaluated);
         }
-
-        Ok(evaluated
-            .into_sorted_vec()
-            .into_iter()
-            .take(num)
-            .map(Into::into)
-            .collect())
     }
 
-    /// Queries the tree to find the nearest element to `point`, using the specified
-    /// distance metric function. Faster than querying for nearest(point, 1, ...) due
-    /// to not needing to allocate a Vec for the result
+    /// Returns statistics describing the shape of the tree, such as its depth and how
+    /// full its leaves are
     ///
     /// # Examples
     ///
     /// ```rust
     /// use kiddo::KdTree;
-    /// use kiddo::distance::squared_euclidean;
     ///
-    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::with_capacity(1)?;
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     ///
-    /// let nearest = tree.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    /// let stats = tree.stats();
     ///
-    /// assert!((nearest.0 - 0.01f64).abs() < f64::EPSILON);
-    /// assert_eq!(*nearest.1, 100);
+    /// assert_eq!(stats.leaves, 2);
+    /// assert_eq!(stats.max_imbalance, 0.5);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    // TODO: pending only ever gets to about 7 items max. try doing this
-    //       recursively to avoid the alloc/dealloc of the vec
-    pub fn nearest_one<F>(&self, point: &[A; K], distance: &F) -> Result<(A, &T), ErrorKind>
-    where
-        F: Fn(&[A; K], &[A; K]) -> A,
-    {
-        if self.size == 0 {
-            return Err(ErrorKind::Empty);
-        }
-        self.check_point(point)?;
-
-        let mut pending = Vec::with_capacity(16);
-
-        let mut best_dist: A = A::infinity();
-        let mut best_elem: Option<&T> = None;
-
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
-
-        while !pending.is_empty() && (best_elem.is_none() || (pending[0].distance < best_dist)) {
-            self.nearest_one_step(
-                point,
-                distance,
-                &mut pending,
-                &mut best_dist,
-                &mut best_elem,
-            );
-        }
-
-        Ok((best_dist, best_elem.unwrap()))
+    pub fn stats(&self) -> TreeStats {
+        TreeStats::collect(self)
     }
 
-        /// Queries the tree to find the nearest element to `point`, using the specified
-    /// distance metric function. Faster than querying for nearest(point, 1, ...) due
-    /// to not needing to allocate a Vec for the result
+    /// Returns an iterator over every element in the tree, together with the coordinates
+    /// at which it is stored. Elements are visited leaf by leaf, from the leftmost leaf to
+    /// the rightmost.
     ///
     /// # Examples
     ///
     /// ```rust
     /// use kiddo::KdTree;
-    /// use kiddo::distance::squared_euclidean;
     ///
-    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
-    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::with_per_node_capacity(1)?;
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     ///
-    /// let nearest = tree.nearest_one_periodic(&[1.0, 2.0, 5.1], &squared_euclidean, &PERIODIC)?;
+    /// let entries: Vec<_> = tree.iter().collect();
     ///
-    /// assert!((nearest.0 - 0.01f64).abs() < f64::EPSILON);
-    /// assert_eq!(*nearest.1, 100);
+    /// assert_eq!(entries, vec![(&[1.0, 2.0, 5.0], &100), (&[2.0, 3.0, 6.0], &101)]);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    // TODO: pending only ever gets to about 7 items max. try doing this
-    //       recursively to avoid the alloc/dealloc of the vec
-    pub fn nearest_one_periodic<F>(&self, point: &[A; K], distance: &F, periodic: &[A; K]) -> Result<(A, &T), ErrorKind>
-    where
-        F: Fn(&[A; K], &[A; K]) -> A,
-    {
-        if self.size == 0 {
-            return Err(ErrorKind::Empty);
-        }
-        self.check_point(point)?;
-
-        let mut pending = Vec::with_capacity(16);
-
-        let mut best_dist: A = A::infinity();
-        let mut best_elem: Option<&T> = None;
-
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
-
-        while !pending.is_empty() && (best_elem.is_none() || (pending[0].distance < best_dist)) {
-            self.nearest_one_step(
-                point,
-                distance,
-                &mut pending,
-                &mut best_dist,
-                &mut best_elem,
-            );
-        }
-
-        // Get canonical_image result as in non-PBC
-        let canonical_image_result = (best_dist, best_elem.unwrap());
-
-        // Find closest dist2 to every side
-        let mut closest_side_dist2: [A; K] = [A::zero(); K];
-        for side in 0..K {
-
-            // Do a single index here. This is equal to distance to lower side
-            let query_component: A = point[side];
-
-            // Get distance to upper half
-            let upper = periodic[side] - query_component;
-
-            // !negative includes zero
-            debug_assert!(!upper.is_negative()); 
-            debug_assert!(!query_component.is_negative());
-
-            // Choose lesser of two and then square
-            closest_side_dist2[side] = upper.min(query_component).powi(2);
-        }
-
-        // Find which images we need to check.
-        // Initialize vector with canonical image (which we will remove later)
-        let mut images_to_check = Vec::with_capacity(2_usize.pow(K as u32)-1);
-        for image in 1..2_usize.pow(K as u32) {
-            
-            // Closest image in the form of bool array
-            let closest_image = (0..K)
-                .map(|idx| ((image / 2_usize.pow(idx as u32)) % 2) == 1);
-
-            // Find distance to corresponding side, edge, vertex or other higher dimensional equivalent
-            let dist_to_side_edge_or_other: A = closest_image
-                .clone()
-                .enumerate()
-                .flat_map(|(side, flag)| if flag {
-                    
-                    // Get minimum of dist2 to lower and upper side
-                    Some(closest_side_dist2[side])
-                } else { None })
-                .fold(A::zero(), |acc, x| acc + x);
-
-            if dist_to_side_edge_or_other < canonical_image_result.0 {
-
-                let mut image_to_check = point.clone();
-                
-                for (idx, flag) in closest_image.enumerate() {
-
-                    // If moving image along this dimension
-                    if flag {
-                        // Do a single index here. This is equal to distance to lower side
-                        let query_component: A = point[idx];
-                        // Single index here as well
-                        let periodic_component = periodic[idx];
-
-                        if query_component < periodic_component / A::from(2_u8).unwrap() {
-                            // Add if in lower half of box
-                            image_to_check[idx] = query_component + periodic_component;
-                        } else {
-                            // Subtract if in upper half of box
-                            image_to_check[idx] = query_component - periodic_component;
-                        }
-                        
-                    }
-                }
-
-                images_to_check.push(image_to_check);
-            }
-        }
-
-        // Then check all images
-        for image in &images_to_check {
-
-            let mut image_pending = Vec::with_capacity(16);
-
-            let mut image_best_dist: A = A::infinity();
-            let mut image_best_elem: Option<&T> = None;
-
-            image_pending.push(HeapElement {
-                distance: A::zero(),
-                element: self,
-            });
-
-            while !image_pending.is_empty() && (image_best_elem.is_none() || (image_pending[0].distance < image_best_dist)) {
-                self.nearest_one_step(
-                    image,
-                    distance,
-                    &mut image_pending,
-                    &mut image_best_dist,
-                    &mut image_best_elem,
-                );
-            }
-
-            if image_best_dist < best_dist {
-                best_dist = image_best_dist;
-                best_elem = image_best_elem;
-            }
-        }
-
-        Ok((best_dist, best_elem.unwrap()))
-    }
-
-    fn within_impl<F>(
-        &self,
-        point: &[A; K],
-        radius: A,
-        distance: &F,
-    ) -> Result<BinaryHeap<HeapElement<A, &T>>, ErrorKind>
-    where
-        F: Fn(&[A; K], &[A; K]) -> A,
-    {
-        self.check_point(point)?;
-
-        let mut pending = BinaryHeap::new();
-        let mut evaluated = BinaryHeap::<HeapElement<A, &T>>::new();
-
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
-
-        while !pending.is_empty() && (-pending.peek().unwrap().distance <= radius) {
-            self.nearest_step(
-                point,
-                self.size,
-                radius,
-                distance,
-                &mut pending,
-                &mut evaluated,
-            );
+    pub fn iter(&self) -> Iter<'_, A, T, K, O> {
+        Iter {
+            stack: vec![self],
+            leaf: [].iter().zip([].iter()),
+            remaining: self.size,
         }
-
-        Ok(evaluated)
     }
 
-    /// Queries the tree to find all elements within `radius` of `point`, using the specified
-    /// distance metric function. Results are returned sorted nearest-first
+    /// Returns an iterator over the coordinates of every element in the tree, in the same
+    /// order as `iter()`
     ///
     /// # Examples
     ///
     /// ```rust
     /// use kiddo::KdTree;
-    /// use kiddo::distance::squared_euclidean;
     ///
     /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
-    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
     ///
-    /// let within = tree.within(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    /// let points: Vec<_> = tree.points().collect();
     ///
-    /// assert_eq!(within.len(), 2);
+    /// assert_eq!(points, vec![&[1.0, 2.0, 5.0], &[2.0, 3.0, 6.0]]);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn within<F>(
-        &self,
-        point: &[A; K],
-        radius: A,
-        distance: &F,
-    ) -> Result<Vec<(A, &T)>, ErrorKind>
-    where
-        F: Fn(&[A; K], &[A; K]) -> A,
-    {
-        if self.size == 0 {
-            return Ok(vec![]);
-        }
-
-        self.within_impl(point, radius, distance).map(|evaluated| {
-            evaluated
-                .into_sorted_vec()
-                .into_iter()
-                .map(Into::into)
-                .collect()
-        })
+    pub fn points(&self) -> Points<'_, A, T, K, O> {
+        Points(self.iter())
     }
 
-    /// Queries the tree to find all elements within `radius` of `point`, using the specified
-    /// distance metric function. Results are returned sorted nearest-first. Obeys periodic
-    /// boundary conditions
+    /// Queries the tree to find the nearest `num` elements to `point`, using the specified
+    /// distance metric function.
     ///
     /// # Examples
     ///
@@ -766,138 +559,39 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// use kiddo::KdTree;
     /// use kiddo::distance::squared_euclidean;
     ///
-    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
     /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
-    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
     ///
-    /// let within = tree.within_periodic(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean, &PERIODIC)?;
+    /// let nearest = tree.nearest(&[1.0, 2.0, 5.1], 1, &squared_euclidean)?;
     ///
-    /// assert_eq!(within.len(), 2);
+    /// assert_eq!(nearest.len(), 1);
+    /// assert!((nearest[0].0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest[0].1, 100);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn within_periodic<F>(
+    pub fn nearest<F>(
         &self,
         point: &[A; K],
-        radius: A,
+        num: usize,
         distance: &F,
-        periodic: &[A; K],
     ) -> Result<Vec<(A, &T)>, ErrorKind>
     where
         F: Fn(&[A; K], &[A; K]) -> A,
     {
-        if self.size == 0 {
-            return Ok(vec![]);
-        }
-
-        // do as in within() but hold off on sorting
-        let mut canonical_result: Vec<(A, &T)> = self.within_impl(point, radius, distance).map(|evaluated| {
-            evaluated
-                .into_vec()
-                .into_iter()
-                .map(Into::into)
-                .collect()
-        })?;
-
-
-        // Find closest dist2 to every side
-        let mut closest_side_dist2: [A; K] = [A::zero(); K];
-        for side in 0..K {
-
-            // Do a single index here. This is equal to distance to lower side
-            let query_component: A = point[side];
-
-            // Get distance to upper half
-            let upper = periodic[side] - query_component;
-
-            // !negative includes zero
-            debug_assert!(!upper.is_negative()); 
-            debug_assert!(!query_component.is_negative());
-
-            // Choose lesser of two and then square
-            closest_side_dist2[side] = upper.min(query_component).powi(2);
-        }
-
-        // Find which images we need to check.
-        // Initialize vector with canonical image (which we will remove later)
-        let mut images_to_check = Vec::with_capacity(2_usize.pow(K as u32)-1);
-        for image in 1..2_usize.pow(K as u32) {
-            
-            // Closest image in the form of bool array
-            let closest_image = (0..K)
-                .map(|idx| ((image / 2_usize.pow(idx as u32)) % 2) == 1);
-
-            // Find distance to corresponding side, edge, vertex or other higher dimensional equivalent
-            let dist_to_side_edge_or_other: A = closest_image
-                .clone()
-                .enumerate()
-                .flat_map(|(side, flag)| if flag {
-                    
-                    // Get minimum of dist2 to lower and upper side
-                    Some(closest_side_dist2[side])
-                } else { None })
-                .fold(A::zero(), |acc, x| acc + x);
-
-            if dist_to_side_edge_or_other < radius {
-
-                let mut image_to_check = point.clone();
-                
-                for (idx, flag) in closest_image.enumerate() {
-
-                    // If moving image along this dimension
-                    if flag {
-                        // Do a single index here. This is equal to distance to lower side
-                        let query_component: A = point[idx];
-                        // Single index here as well
-                        let periodic_component = periodic[idx];
-
-                        if query_component < periodic_component / A::from(2_u8).unwrap() {
-                            // Add if in lower half of box
-                            image_to_check[idx] = query_component + periodic_component;
-                        } else {
-                            // Subtract if in upper half of box
-                            image_to_check[idx] = query_component - periodic_component;
-                        }
-                        
-                    }
-                }
-
-                images_to_check.push(image_to_check);
-            }
-        }
-
-        // Then check all images
-        for image in &images_to_check {
-
-            let mut image_pending = Vec::with_capacity(16);
-
-            image_pending.push(HeapElement {
-                distance: A::zero(),
-                element: self,
-            });
-
-            // Get all points within the radius for this image
-            let mut image_result = self.within_impl(image, radius, distance).map(|evaluated| {
+        self.nearest_impl(point, num, A::infinity(), &self.metric(distance), &|_| true)
+            .map(|evaluated| {
                 evaluated
-                    .into_vec()
                     .into_iter()
-                    .map(Into::into)
+                    .map(|x| (x.distance, x.element.1))
                     .collect()
-            })?;
-
-            canonical_result.append(&mut image_result)
-        }
-
-        // Now sort
-        canonical_result.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-
-        Ok(canonical_result)
+            })
     }
 
-    /// Queries the tree to find all elements within `radius` of `point`, using the specified
-    /// distance metric function. Results are returned in arbitrary order. Faster than within()
+    /// Queries the tree to find the nearest `num` elements to `point` that are no further
+    /// than `max_radius` from it, using the specified distance metric function. Results are
+    /// returned sorted nearest-first.
     ///
     /// # Examples
     ///
@@ -911,31 +605,35 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     /// tree.add(&[200.0, 300.0, 600.0], 102)?;
     ///
-    /// let within = tree.within(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    /// let nearest = tree.nearest_within(&[1.0, 2.0, 5.1], 3, 10f64, &squared_euclidean)?;
     ///
-    /// assert_eq!(within.len(), 2);
+    /// assert_eq!(nearest.len(), 2);
+    /// assert_eq!(*nearest[0].1, 100);
+    /// assert_eq!(*nearest[1].1, 101);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn within_unsorted<F>(
+    pub fn nearest_within<F>(
         &self,
         point: &[A; K],
-        radius: A,
+        num: usize,
+        max_radius: A,
         distance: &F,
     ) -> Result<Vec<(A, &T)>, ErrorKind>
     where
         F: Fn(&[A; K], &[A; K]) -> A,
     {
-        if self.size == 0 {
-            return Ok(vec![]);
-        }
-
-        self.within_impl(point, radius, distance)
-            .map(|evaluated| evaluated.into_vec().into_iter().map(Into::into).collect())
+        self.nearest_impl(point, num, max_radius, &self.metric(distance), &|_| true)
+            .map(|evaluated| {
+                evaluated
+                    .into_iter()
+                    .map(|x| (x.distance, x.element.1))
+                    .collect()
+            })
     }
 
-        /// Queries the tree to find all elements within `radius` of `point`, using the specified
-    /// distance metric function. Results are returned sorted nearest-first. Obeys periodic
-    /// boundary conditions
+    /// Queries the tree to find the nearest `num` elements to `point` for which `filter`
+    /// returns `true`, using the specified distance metric function. Rejected elements are
+    /// skipped as the tree is traversed, so they do not count towards `num`.
     ///
     /// # Examples
     ///
@@ -943,135 +641,43 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// use kiddo::KdTree;
     /// use kiddo::distance::squared_euclidean;
     ///
-    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
     /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     /// tree.add(&[200.0, 300.0, 600.0], 102)?;
     ///
-    /// let within = tree.within_unsorted_periodic(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean, &PERIODIC)?;
+    /// let nearest =
+    ///     tree.nearest_filtered(&[1.0, 2.0, 5.1], 2, &squared_euclidean, |&item| item != 100)?;
     ///
-    /// assert_eq!(within.len(), 2);
+    /// assert_eq!(nearest.len(), 2);
+    /// assert_eq!(*nearest[0].1, 101);
+    /// assert_eq!(*nearest[1].1, 102);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn within_unsorted_periodic<F>(
+    pub fn nearest_filtered<F, P>(
         &self,
         point: &[A; K],
-        radius: A,
+        num: usize,
         distance: &F,
-        periodic: &[A; K],
+        filter: P,
     ) -> Result<Vec<(A, &T)>, ErrorKind>
     where
         F: Fn(&[A; K], &[A; K]) -> A,
+        P: Fn(&T) -> bool,
     {
-        if self.size == 0 {
-            return Ok(vec![]);
-        }
-
-        let mut canonical_result: Vec<(A, &T)> = self.within_impl(point, radius, distance).map(|evaluated| {
-            evaluated
-                .into_vec()
-                .into_iter()
-                .map(Into::into)
-                .collect()
-        })?;
-
-
-        // Find closest dist2 to every side
-        let mut closest_side_dist2: [A; K] = [A::zero(); K];
-        for side in 0..K {
-
-            // Do a single index here. This is equal to distance to lower side
-            let query_component: A = point[side];
-
-            // Get distance to upper half
-            let upper = periodic[side] - query_component;
-
-            // !negative includes zero
-            debug_assert!(!upper.is_negative()); 
-            debug_assert!(!query_component.is_negative());
-
-            // Choose lesser of two and then square
-            closest_side_dist2[side] = upper.min(query_component).powi(2);
-        }
-
-        // Find which images we need to check.
-        // Initialize vector with canonical image (which we will remove later)
-        let mut images_to_check = Vec::with_capacity(2_usize.pow(K as u32)-1);
-        for image in 1..2_usize.pow(K as u32) {
-            
-            // Closest image in the form of bool array
-            let closest_image = (0..K)
-                .map(|idx| ((image / 2_usize.pow(idx as u32)) % 2) == 1);
-
-            // Find distance to corresponding side, edge, vertex or other higher dimensional equivalent
-            let dist_to_side_edge_or_other: A = closest_image
-                .clone()
-                .enumerate()
-                .flat_map(|(side, flag)| if flag {
-                    
-                    // Get minimum of dist2 to lower and upper side
-                    Some(closest_side_dist2[side])
-                } else { None })
-                .fold(A::zero(), |acc, x| acc + x);
-
-            if dist_to_side_edge_or_other < radius {
-
-                let mut image_to_check = point.clone();
-                
-                for (idx, flag) in closest_image.enumerate() {
-
-                    // If moving image along this dimension
-                    if flag {
-                        // Do a single index here. This is equal to distance to lower side
-                        let query_component: A = point[idx];
-                        // Single index here as well
-                        let periodic_component = periodic[idx];
-
-                        if query_component < periodic_component / A::from(2_u8).unwrap() {
-                            // Add if in lower half of box
-                            image_to_check[idx] = query_component + periodic_component;
-                        } else {
-                            // Subtract if in upper half of box
-                            image_to_check[idx] = query_component - periodic_component;
-                        }
-                        
-                    }
-                }
-
-                images_to_check.push(image_to_check);
-            }
-        }
-
-        // Then check all images
-        for image in &images_to_check {
-
-            let mut image_pending = Vec::with_capacity(16);
-
-            image_pending.push(HeapElement {
-                distance: A::zero(),
-                element: self,
-            });
-
-            // Get all points within the radius for this image
-            let mut image_result = self.within_impl(image, radius, distance).map(|evaluated| {
+        self.nearest_impl(point, num, A::infinity(), &self.metric(distance), &filter)
+            .map(|evaluated| {
                 evaluated
-                    .into_vec()
                     .into_iter()
-                    .map(Into::into)
+                    .map(|x| (x.distance, x.element.1))
                     .collect()
-            })?;
-
-            canonical_result.append(&mut image_result)
-        }
-
-        Ok(canonical_result)
+            })
     }
 
-    /// Queries the tree to find the best `n` elements within `radius` of `point`, using the specified
-    /// distance metric function. Results are returned in arbitrary order. 'Best' is determined by
-    /// performing a comparison of the elements using < (ie, std::ord::lt)
+    /// Queries the tree to find the nearest `num` elements to `point`, using the specified
+    /// distance metric function. Each result also contains the coordinates at which the
+    /// element is stored.
     ///
     /// # Examples
     ///
@@ -1082,57 +688,31 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
-    /// tree.add(&[2.0, 3.0, 6.0], 1)?;
-    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     ///
-    /// let best_n_within = tree.best_n_within(&[1.0, 2.0, 5.0], 10f64, 1, &squared_euclidean)?;
+    /// let nearest = tree.nearest_with_point(&[1.0, 2.0, 5.1], 1, &squared_euclidean)?;
     ///
-    /// assert_eq!(best_n_within[0], 1);
+    /// assert_eq!(nearest.len(), 1);
+    /// assert!((nearest[0].0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest[0].1, [1.0, 2.0, 5.0]);
+    /// assert_eq!(*nearest[0].2, 100);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn best_n_within<F>(
+    pub fn nearest_with_point<F>(
         &self,
         point: &[A; K],
-        radius: A,
-        max_qty: usize,
+        num: usize,
         distance: &F,
-    ) -> Result<Vec<T>, ErrorKind>
+    ) -> Result<Vec<PointResult<'_, A, T, K>>, ErrorKind>
     where
         F: Fn(&[A; K], &[A; K]) -> A,
-        T: Copy + Ord,
     {
-        if self.size == 0 {
-            return Ok(vec![]);
-        }
-
-        self.check_point(point)?;
-
-        let mut pending = Vec::with_capacity(max_qty);
-        let mut evaluated = BinaryHeap::<T>::new();
-
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
-
-        while !pending.is_empty() {
-            self.best_n_within_step(
-                point,
-                self.size,
-                max_qty,
-                radius,
-                distance,
-                &mut pending,
-                &mut evaluated,
-            );
-        }
-
-        Ok(evaluated.into_vec().into_iter().collect())
+        self.nearest_impl(point, num, A::infinity(), &self.metric(distance), &|_| true)
+            .map(|evaluated| evaluated.into_iter().map(Into::into).collect())
     }
 
-    /// Queries the tree to find the best `n` elements within `radius` of `point`, using the specified
-    /// distance metric function. Results are returned in arbitrary order. 'Best' is determined by
-    /// performing a comparison of the elements using < (ie, std::ord::lt). Returns an iterator.
+    /// Queries the tree to find the nearest `num` elements to `point`, using the specified
+    /// distance metric function, and also reports how much work the query did.
     ///
     /// # Examples
     ///
@@ -1143,220 +723,2148 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
     ///
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
-    /// tree.add(&[2.0, 3.0, 6.0], 1)?;
-    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     ///
-    /// let mut best_n_within_iter = tree.best_n_within_into_iter(&[1.0, 2.0, 5.0], 10f64, 1, &squared_euclidean);
-    /// let first = best_n_within_iter.next().unwrap();
+    /// let (nearest, stats) = tree.nearest_with_stats(&[1.0, 2.0, 5.1], 1, &squared_euclidean)?;
     ///
-    /// assert_eq!(first, 1);
+    /// assert_eq!(*nearest[0].1, 100);
+    /// assert_eq!(stats.leaves_visited, 1);
+    /// assert_eq!(stats.distance_evaluations, 2);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn best_n_within_into_iter<F>(
+    #[allow(clippy::type_complexity)]
+    pub fn nearest_with_stats<F>(
         &self,
         point: &[A; K],
-        radius: A,
-        max_qty: usize,
+        num: usize,
         distance: &F,
-    ) -> impl Iterator<Item = T>
+    ) -> Result<(Vec<(A, &T)>, QueryStats), ErrorKind>
     where
         F: Fn(&[A; K], &[A; K]) -> A,
-        T: Copy + Ord,
     {
-        // if let Err(err) = self.check_point(point) {
-        //     return Err(err);
-        // }
-        // if self.size == 0 {
-        //     return std::iter::empty::<T>();
-        // }
+        let stats = Cell::new(QueryStats::default());
+        let metric = self.metric(distance).with_stats(&stats);
 
-        let mut pending = Vec::with_capacity(max_qty);
-        let mut evaluated = BinaryHeap::<T>::new();
+        let nearest = self
+            .nearest_impl(point, num, A::infinity(), &metric, &|_| true)?
+            .into_iter()
+            .map(|x| (x.distance, x.element.1))
+            .collect();
 
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
+        Ok((nearest, stats.get()))
+    }
 
-        while !pending.is_empty() {
-            self.best_n_within_step(
-                point,
-                self.size,
-                max_qty,
-                radius,
-                distance,
-                &mut pending,
-                &mut evaluated,
-            );
-        }
+    fn nearest_impl<F, P>(
+        &self,
+        point: &[A; K],
+        num: usize,
+        max_dist: A,
+        metric: &Metric<A, F, K>,
+        filter: &P,
+    ) -> Result<Vec<Candidate<'_, A, T, K>>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Fn(&T) -> bool,
+    {
+        let mut pending = BinaryHeap::new();
+        let mut evaluated = BinaryHeap::new();
 
-        evaluated.into_iter()
+        self.nearest_search(
+            point,
+            num,
+            max_dist,
+            metric,
+            filter,
+            &mut pending,
+            &mut evaluated,
+        )?;
+
+        Ok(evaluated.into_sorted_vec())
     }
 
-    fn best_n_within_step<'b, F>(
+    // Pairs `distance` with a periodic box supplied to one of the `*_periodic` queries,
+    // after checking that the box is usable and that `point` lies within it. The box
+    // overrides the tree's own, so `point` need not lie within that.
+    fn periodic_metric<'a, F>(
         &self,
         point: &[A; K],
-        _num: usize,
-        max_qty: usize,
+        distance: &'a F,
+        periodic: PeriodicBox<A, K>,
+    ) -> Result<Metric<'a, A, F, K>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        periodic.validate()?;
+        let metric = Metric::new(distance, Some(periodic));
+        metric.check_point(point, Operation::Query)?;
+
+        Ok(metric)
+    }
+
+    /// Runs a `nearest` query, leaving the results in `evaluated`. Elements further
+    /// than `max_dist` from `point`, or rejected by `filter`, are ignored. Both buffers
+    /// are cleared first, so they can be reused across queries.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn nearest_search<'t, F, P>(
+        &'t self,
+        point: &[A; K],
+        num: usize,
         max_dist: A,
-        distance: &F,
-        pending: &mut Vec<HeapElement<A, &'b Self>>,
-        evaluated: &mut BinaryHeap<T>,
-    ) where
+        metric: &Metric<A, F, K>,
+        filter: &P,
+        pending: &mut BinaryHeap<HeapElement<A, &'t Self>>,
+        evaluated: &mut BinaryHeap<Candidate<'t, A, T, K>>,
+    ) -> Result<(), ErrorKind>
+    where
         F: Fn(&[A; K], &[A; K]) -> A,
-        T: Copy + Ord,
+        P: Fn(&T) -> bool,
     {
-        let curr = &mut &*pending.pop().unwrap().element;
-        <KdTree<A, T, K>>::populate_pending(point, max_dist, distance, pending, curr);
+        pending.clear();
+        evaluated.clear();
 
-        match &curr.content {
-            Node::Leaf { points, bucket, .. } => {
-                let points = points.iter();
-                let bucket = bucket.iter();
-                let iter = points.zip(bucket).map(|(p, d)| HeapElement {
-                    distance: self.get_distance(point, p, distance),
-                    element: d,
-                });
+        metric.check_point(point, Operation::Query)?;
 
-                for element in iter {
-                    if element <= max_dist {
-                        if evaluated.len() < max_qty {
-                            evaluated.push(*element.element);
-                        } else {
-                            let mut top = evaluated.peek_mut().unwrap();
-                            if element.element < &top {
-                                *top = *element.element;
-                            }
-                        }
-                    }
-                }
-            }
-            Node::Stem { .. } => unreachable!(),
+        let num = core::cmp::min(num, self.size);
+        if num == 0 {
+            return Ok(());
+        }
+
+        pending.push(HeapElement {
+            distance: A::zero(),
+            element: self,
+        });
+
+        while !pending.is_empty()
+            && (evaluated.len() < num
+                || (-pending.peek().unwrap().distance <= evaluated.peek().unwrap().distance))
+        {
+            self.nearest_step(point, num, max_dist, metric, filter, pending, evaluated);
         }
+        metric.record(|stats| stats.pruned_subtrees += pending.len());
+
+        Ok(())
     }
 
-    fn nearest_step<'b, F>(
+    /// Queries the tree to find the nearest `num` elements to `point`, using the specified
+    /// distance metric function. Obeys periodic boundary conditions.
+    ///
+    /// `periodic` is either the size of a box with its lower corner at the origin, or a
+    /// [`PeriodicBox`], in which case only the axes it marks as periodic wrap around.
+    /// The query point, and every element of the tree, must lie within the box along
+    /// those axes. On a tree created with `new_periodic()`, `periodic` takes the place of
+    /// the tree's own box for this query only.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::{KdTree, PeriodicBox};
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[1.0, 2.0, 9.5], 102)?;
+    ///
+    /// let nearest = tree.nearest_periodic(&[1.0, 2.0, 5.1], 1, &squared_euclidean, &PERIODIC)?;
+    ///
+    /// assert_eq!(nearest.len(), 1);
+    /// assert!((nearest[0].0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest[0].1, 100);
+    ///
+    /// let nearest = tree.nearest_periodic(&[1.0, 2.0, 0.5], 1, &squared_euclidean, &PERIODIC)?;
+    /// assert_eq!(*nearest[0].1, 102);
+    ///
+    /// // when the third axis does not wrap, 102 is no longer just across the boundary
+    /// let periodic = PeriodicBox::new([0.0; 3], PERIODIC).with_axes([true, true, false]);
+    /// let nearest = tree.nearest_periodic(&[1.0, 2.0, 0.5], 1, &squared_euclidean, &periodic)?;
+    /// assert_eq!(*nearest[0].1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_periodic<F, P>(
         &self,
         point: &[A; K],
         num: usize,
-        max_dist: A,
         distance: &F,
-        pending: &mut BinaryHeap<HeapElement<A, &'b Self>>,
-        evaluated: &mut BinaryHeap<HeapElement<A, &'b T>>,
-    ) where
+        periodic: &P,
+    ) -> Result<Vec<(A, &T)>, ErrorKind>
+    where
         F: Fn(&[A; K], &[A; K]) -> A,
+        P: Into<PeriodicBox<A, K>> + Copy,
     {
-        let curr = &mut &*pending.pop().unwrap().element;
-        <KdTree<A, T, K>>::populate_pending(point, max_dist, distance, pending, curr);
+        let metric = self.periodic_metric(point, distance, (*periodic).into())?;
 
-        match &curr.content {
-            Node::Leaf { points, bucket, .. } => {
-                let points = points.iter();
-                let bucket = bucket.iter();
-                let iter = points.zip(bucket).map(|(p, d)| HeapElement {
-                    distance: self.get_distance(point, p, distance),
-                    element: d,
-                });
+        self.nearest_impl(point, num, A::infinity(), &metric, &|_| true)
+            .map(|evaluated| {
+                evaluated
+                    .into_iter()
+                    .map(|x| (x.distance, x.element.1))
+                    .collect()
+            })
+    }
 
-                for element in iter {
-                    if element <= max_dist {
-                        if evaluated.len() < num {
-                            evaluated.push(element);
-                        } else {
-                            let mut top = evaluated.peek_mut().unwrap();
-                            if element < *top {
-                                *top = element;
-                            }
-                        }
-                    }
-                }
-            }
-            Node::Stem { .. } => unreachable!(),
-        }
+    /// Queries the tree to find the nearest element to `point`, using the specified
+    /// distance metric function. Faster than querying for nearest(point, 1, ...) due
+    /// to not needing to allocate a Vec for the result
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let nearest = tree.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// assert!((nearest.0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    // TODO: pending only ever gets to about 7 items max. try doing this
+    //       recursively to avoid the alloc/dealloc of the vec
+    pub fn nearest_one<F>(&self, point: &[A; K], distance: &F) -> Result<(A, &T), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.nearest_one_search(
+            point,
+            &self.metric(distance),
+            &|_| true,
+            &mut Vec::with_capacity(16),
+        )
     }
 
-    fn get_distance<F>(
+    /// Queries the tree to find the nearest element to `point` for which `filter` returns
+    /// `true`, using the specified distance metric function. Rejected elements are skipped
+    /// as the tree is traversed.
+    ///
+    /// Returns `ErrorKind::Empty` if no element in the tree is accepted by `filter`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let nearest =
+    ///     tree.nearest_one_filtered(&[1.0, 2.0, 5.1], &squared_euclidean, |&item| item != 100)?;
+    ///
+    /// assert!((nearest.0 - 2.81f64).abs() < 1e-10);
+    /// assert_eq!(*nearest.1, 101);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_one_filtered<F, P>(
         &self,
-        a: &[A; K],
-        b: &[A; K],
+        point: &[A; K],
         distance: &F,
-    ) -> A
+        filter: P,
+    ) -> Result<(A, &T), ErrorKind>
     where
         F: Fn(&[A; K], &[A; K]) -> A,
+        P: Fn(&T) -> bool,
     {
-        get_distance(a, b, distance, self.periodic)
+        self.nearest_one_search(
+            point,
+            &self.metric(distance),
+            &filter,
+            &mut Vec::with_capacity(16),
+        )
     }
 
-    fn nearest_one_step<'b, F>(
+    /// Queries the tree to find the nearest element to `point`, using the specified
+    /// distance metric function, and also reports how much work the query did.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::with_per_node_capacity(1)?;
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let (nearest, stats) = tree.nearest_one_with_stats(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// assert_eq!(*nearest.1, 100);
+    /// assert!(stats.stems_visited > 0);
+    /// assert!(stats.pruned_subtrees > 0);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_one_with_stats<F>(
         &self,
         point: &[A; K],
         distance: &F,
-        pending: &mut Vec<HeapElement<A, &'b Self>>,
-        best_dist: &mut A,
-        best_elem: &mut Option<&'b T>,
-    ) where
+    ) -> Result<((A, &T), QueryStats), ErrorKind>
+    where
         F: Fn(&[A; K], &[A; K]) -> A,
     {
-        let curr = &mut &*pending.pop().unwrap().element;
-        let evaluated_dist = *best_dist;
-        <KdTree<A, T, K>>::populate_pending(point, evaluated_dist, distance, pending, curr);
+        let stats = Cell::new(QueryStats::default());
+        let metric = self.metric(distance).with_stats(&stats);
 
-        match &curr.content {
-            Node::Leaf { points, bucket, .. } => {
-                let points = points.iter();
-                let bucket = bucket.iter();
-                let iter = points.zip(bucket).map(|(p, d)| HeapElement {
-                    distance: self.get_distance(point, p, distance),
-                    element: d,
-                });
+        let nearest =
+            self.nearest_one_search(point, &metric, &|_| true, &mut Vec::with_capacity(16))?;
 
-                for element in iter {
-                    if best_elem.is_none() || element < *best_dist {
-                        *best_elem = Some(element.element);
-                        *best_dist = element.distance;
-                    }
-                }
-            }
-            Node::Stem { .. } => unreachable!(),
-        }
+        Ok((nearest, stats.get()))
     }
 
-    fn populate_pending<'a, F>(
+    /// Runs a `nearest_one` query using `pending` as its work list, skipping elements
+    /// rejected by `filter`. The buffer is cleared first, so it can be reused across queries.
+    pub(crate) fn nearest_one_search<'t, F, P>(
+        &'t self,
         point: &[A; K],
-        max_dist: A,
-        distance: &F,
-        pending: &mut impl Stack<HeapElement<A, &'a Self>>,
-        curr: &mut &'a Self,
-    ) where
+        metric: &Metric<A, F, K>,
+        filter: &P,
+        pending: &mut Vec<HeapElement<A, &'t Self>>,
+    ) -> Result<(A, &'t T), ErrorKind>
+    where
         F: Fn(&[A; K], &[A; K]) -> A,
+        P: Fn(&T) -> bool,
     {
-        while let Node::Stem { left, right, .. } = &curr.content {
-            let candidate;
-            if curr.belongs_in_left(point) {
-                candidate = right;
-                *curr = left;
-            } else {
-                candidate = left;
-                *curr = right;
-            };
+        pending.clear();
 
-            let candidate_to_space = util::distance_to_space(
-                    point,
-                    &candidate.min_bounds,
-                    &candidate.max_bounds,
-                    distance,
-                );
+        if self.size == 0 {
+            return Err(ErrorKind::Empty);
+        }
+        metric.check_point(point, Operation::Query)?;
 
-            if candidate_to_space <= max_dist {
-                pending.stack_push(HeapElement {
-                    distance: candidate_to_space * -A::one(),
-                    element: &**candidate,
-                });
+        let mut best_dist: A = A::infinity();
+        let mut best_elem: Option<&T> = None;
+
+        pending.push(HeapElement {
+            distance: A::zero(),
+            element: self,
+        });
+
+        while !pending.is_empty() && (best_elem.is_none() || (pending[0].distance < best_dist)) {
+            self.nearest_one_step(
+                point,
+                metric,
+                filter,
+                pending,
+                &mut best_dist,
+                &mut best_elem,
+            );
+        }
+        metric.record(|stats| stats.pruned_subtrees += pending.len());
+
+        best_elem
+            .map(|elem| (best_dist, elem))
+            .ok_or(ErrorKind::Empty)
+    }
+
+    /// Queries the tree to find the nearest `N` elements to `point`, using the specified
+    /// distance metric function. Results are returned sorted nearest-first in a fixed-size
+    /// array. As the candidates are held in a sorted array on the stack rather than in a
+    /// `BinaryHeap`, this is faster than `nearest()` for small values of `N`.
+    ///
+    /// Returns `ErrorKind::Empty` if the tree contains fewer than `N` elements.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let [first, second] = tree.nearest_n::<_, 2>(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// assert_eq!(*first.1, 100);
+    /// assert_eq!(*second.1, 101);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_n<F, const N: usize>(
+        &self,
+        point: &[A; K],
+        distance: &F,
+    ) -> Result<[(A, &T); N], ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if self.size < N {
+            return Err(ErrorKind::Empty);
+        }
+        self.check_point(point, Operation::Query)?;
+
+        let mut best: [(A, Option<&T>); N] = [(A::infinity(), None); N];
+        if N > 0 {
+            self.nearest_n_recurse(point, &self.metric(distance), &mut best);
+        }
+
+        Ok(best.map(|(dist, elem)| (dist, elem.unwrap())))
+    }
+
+    /// Queries the tree to find the nearest element to `point`, using the specified
+    /// distance metric function. Obeys periodic boundary conditions, as described for
+    /// `nearest_periodic()`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let nearest = tree.nearest_one_periodic(&[1.0, 2.0, 5.1], &squared_euclidean, &PERIODIC)?;
+    ///
+    /// assert!((nearest.0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    // TODO: pending only ever gets to about 7 items max. try doing this
+    //       recursively to avoid the alloc/dealloc of the vec
+    pub fn nearest_one_periodic<F, P>(
+        &self,
+        point: &[A; K],
+        distance: &F,
+        periodic: &P,
+    ) -> Result<(A, &T), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Into<PeriodicBox<A, K>> + Copy,
+    {
+        let metric = self.periodic_metric(point, distance, (*periodic).into())?;
+
+        self.nearest_one_search(point, &metric, &|_| true, &mut Vec::with_capacity(16))
+    }
+
+    fn within_impl<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        metric: &Metric<A, F, K>,
+    ) -> Result<BinaryHeap<Candidate<'_, A, T, K>>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let mut pending = BinaryHeap::new();
+        let mut evaluated = BinaryHeap::new();
+
+        self.within_search(point, radius, metric, &mut pending, &mut evaluated)?;
+
+        Ok(evaluated)
+    }
+
+    /// Runs a `within` query, leaving the results in `evaluated`. Both buffers are
+    /// cleared first, so they can be reused across queries.
+    pub(crate) fn within_search<'t, F>(
+        &'t self,
+        point: &[A; K],
+        radius: A,
+        metric: &Metric<A, F, K>,
+        pending: &mut BinaryHeap<HeapElement<A, &'t Self>>,
+        evaluated: &mut BinaryHeap<Candidate<'t, A, T, K>>,
+    ) -> Result<(), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        pending.clear();
+        evaluated.clear();
+
+        metric.check_point(point, Operation::Query)?;
+
+        pending.push(HeapElement {
+            distance: A::zero(),
+            element: self,
+        });
+
+        while !pending.is_empty() && (-pending.peek().unwrap().distance <= radius) {
+            self.nearest_step(
+                point,
+                self.size,
+                radius,
+                metric,
+                &|_| true,
+                pending,
+                evaluated,
+            );
+        }
+        metric.record(|stats| stats.pruned_subtrees += pending.len());
+
+        Ok(())
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned sorted nearest-first
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let within = tree.within(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<Vec<(A, &T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        self.within_impl(point, radius, &self.metric(distance))
+            .map(|evaluated| {
+                evaluated
+                    .into_sorted_vec()
+                    .into_iter()
+                    .map(|x| (x.distance, x.element.1))
+                    .collect()
+            })
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned sorted nearest-first, and each result
+    /// also contains the coordinates at which the element is stored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let within = tree.within_with_point(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// assert_eq!(*within[1].1, [2.0, 3.0, 6.0]);
+    /// assert_eq!(*within[1].2, 101);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within_with_point<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<Vec<PointResult<'_, A, T, K>>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        self.within_impl(point, radius, &self.metric(distance))
+            .map(|evaluated| {
+                evaluated
+                    .into_sorted_vec()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect()
+            })
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function, and also reports how much work the query did. Results are
+    /// returned sorted nearest-first
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::with_per_node_capacity(1)?;
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let (within, stats) = tree.within_with_stats(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// assert_eq!(stats.leaves_visited, 2);
+    /// assert_eq!(stats.pruned_subtrees, 1);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn within_with_stats<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<(Vec<(A, &T)>, QueryStats), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if self.size == 0 {
+            return Ok((vec![], QueryStats::default()));
+        }
+
+        let stats = Cell::new(QueryStats::default());
+        let metric = self.metric(distance).with_stats(&stats);
+
+        let within = self
+            .within_impl(point, radius, &metric)?
+            .into_sorted_vec()
+            .into_iter()
+            .map(|x| (x.distance, x.element.1))
+            .collect();
+
+        Ok((within, stats.get()))
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned sorted nearest-first. Obeys periodic
+    /// boundary conditions, as described for `nearest_periodic()`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let within = tree.within_periodic(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean, &PERIODIC)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within_periodic<F, P>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+        periodic: &P,
+    ) -> Result<Vec<(A, &T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Into<PeriodicBox<A, K>> + Copy,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        let metric = self.periodic_metric(point, distance, (*periodic).into())?;
+
+        self.within_impl(point, radius, &metric).map(|evaluated| {
+            evaluated
+                .into_sorted_vec()
+                .into_iter()
+                .map(|x| (x.distance, x.element.1))
+                .collect()
+        })
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned in arbitrary order. Faster than within()
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let within = tree.within(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within_unsorted<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<Vec<(A, &T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        self.within_impl(point, radius, &self.metric(distance))
+            .map(|evaluated| {
+                evaluated
+                    .into_vec()
+                    .into_iter()
+                    .map(|x| (x.distance, x.element.1))
+                    .collect()
+            })
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned in arbitrary order. Obeys periodic
+    /// boundary conditions, as described for `nearest_periodic()`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let within = tree.within_unsorted_periodic(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean, &PERIODIC)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within_unsorted_periodic<F, P>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+        periodic: &P,
+    ) -> Result<Vec<(A, &T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Into<PeriodicBox<A, K>> + Copy,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        let metric = self.periodic_metric(point, distance, (*periodic).into())?;
+
+        self.within_impl(point, radius, &metric).map(|evaluated| {
+            evaluated
+                .into_vec()
+                .into_iter()
+                .map(|x| (x.distance, x.element.1))
+                .collect()
+        })
+    }
+
+    /// Queries the tree to find the best `n` elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned in arbitrary order. 'Best' is determined by
+    /// performing a comparison of the elements using < (ie, std::ord::lt)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 1)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let best_n_within = tree.best_n_within(&[1.0, 2.0, 5.0], 10f64, 1, &squared_euclidean)?;
+    ///
+    /// assert_eq!(best_n_within[0], 1);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn best_n_within<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        max_qty: usize,
+        distance: &F,
+    ) -> Result<Vec<T>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        T: Copy + Ord,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        self.check_point(point, Operation::Query)?;
+
+        Ok(self
+            .best_n_within_impl(point, radius, max_qty, &self.metric(distance), &|d| d)
+            .into_vec()
+            .into_iter()
+            .map(|x| *x.rank)
+            .collect())
+    }
+
+    /// Queries the tree to find the best `n` elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned in arbitrary order. 'Best' is determined as
+    /// for `best_n_within()`. Obeys periodic boundary conditions, as described for
+    /// `nearest_periodic()`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[9.0, 2.0, 5.0], 1)?;
+    ///
+    /// let best_n_within =
+    ///     tree.best_n_within_periodic(&[1.0, 2.0, 5.0], 10f64, 1, &squared_euclidean, &PERIODIC)?;
+    ///
+    /// assert_eq!(best_n_within[0], 1);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn best_n_within_periodic<F, P>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        max_qty: usize,
+        distance: &F,
+        periodic: &P,
+    ) -> Result<Vec<T>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Into<PeriodicBox<A, K>> + Copy,
+        T: Copy + Ord,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        let metric = self.periodic_metric(point, distance, (*periodic).into())?;
+
+        Ok(self
+            .best_n_within_impl(point, radius, max_qty, &metric, &|d| d)
+            .into_vec()
+            .into_iter()
+            .map(|x| *x.rank)
+            .collect())
+    }
+
+    /// Queries the tree to find the best `n` elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned in arbitrary order. 'Best' is determined by
+    /// performing a comparison of the elements using < (ie, std::ord::lt). Each result also
+    /// contains the distance to, and the coordinates of, the element.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 1)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let best_n_within =
+    ///     tree.best_n_within_with_point(&[1.0, 2.0, 5.0], 10f64, 1, &squared_euclidean)?;
+    ///
+    /// assert_eq!(best_n_within[0], (3f64, &[2.0, 3.0, 6.0], &1));
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn best_n_within_with_point<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        max_qty: usize,
+        distance: &F,
+    ) -> Result<Vec<PointResult<'_, A, T, K>>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        T: Ord,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        self.check_point(point, Operation::Query)?;
+
+        Ok(self
+            .best_n_within_impl(point, radius, max_qty, &self.metric(distance), &|d| d)
+            .into_vec()
+            .into_iter()
+            .map(|x| x.element)
+            .collect())
+    }
+
+    /// Queries the tree to find the best `n` elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned in arbitrary order. 'Best' is determined by
+    /// performing a comparison of the elements using < (ie, std::ord::lt). Returns an iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 1)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let mut best_n_within_iter = tree.best_n_within_into_iter(&[1.0, 2.0, 5.0], 10f64, 1, &squared_euclidean);
+    /// let first = best_n_within_iter.next().unwrap();
+    ///
+    /// assert_eq!(first, 1);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn best_n_within_into_iter<F>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        max_qty: usize,
+        distance: &F,
+    ) -> impl Iterator<Item = T>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        T: Copy + Ord,
+    {
+        // if let Err(err) = self.check_point(point, Operation::Query) {
+        //     return Err(err);
+        // }
+        // if self.size == 0 {
+        //     return std::iter::empty::<T>();
+        // }
+
+        self.best_n_within_impl(point, radius, max_qty, &self.metric(distance), &|d| d)
+            .into_iter()
+            .map(|x| *x.rank)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Queries the tree to find the best `n` elements within `radius` of `point`, using the specified
+    /// distance metric function. 'Best' is determined by comparing the keys returned by `key` for
+    /// each element, with lower keys being better; wrap the key in `std::cmp::Reverse` to prefer
+    /// higher ones. Results are returned sorted best-first.
+    ///
+    /// Unlike `best_n_within()`, the elements themselves need not be `Copy` or `Ord`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::cmp::Reverse;
+    ///
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, (String, u32), 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], ("a".to_string(), 10))?;
+    /// tree.add(&[2.0, 3.0, 6.0], ("b".to_string(), 30))?;
+    /// tree.add(&[200.0, 300.0, 600.0], ("c".to_string(), 50))?;
+    ///
+    /// let best = tree.best_n_within_by_key(
+    ///     &[1.0, 2.0, 5.0],
+    ///     10f64,
+    ///     1,
+    ///     &squared_euclidean,
+    ///     |item| Reverse(item.1),
+    /// )?;
+    ///
+    /// assert_eq!(best.len(), 1);
+    /// assert_eq!(best[0].1.0, "b");
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn best_n_within_by_key<F, G, R>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        max_qty: usize,
+        distance: &F,
+        key: G,
+    ) -> Result<Vec<(A, &T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        G: Fn(&T) -> R,
+        R: Ord,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+
+        self.check_point(point, Operation::Query)?;
+
+        Ok(self
+            .best_n_within_impl(point, radius, max_qty, &self.metric(distance), &|d| key(d))
+            .into_sorted_vec()
+            .into_iter()
+            .map(|x| (x.element.0, x.element.2))
+            .collect())
+    }
+
+    fn best_n_within_impl<'t, F, G, R>(
+        &'t self,
+        point: &[A; K],
+        radius: A,
+        max_qty: usize,
+        metric: &Metric<A, F, K>,
+        key: &G,
+    ) -> BinaryHeap<RankedElement<R, PointResult<'t, A, T, K>>>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        G: Fn(&'t T) -> R,
+        R: Ord,
+    {
+        let mut pending = Vec::with_capacity(max_qty);
+        let mut evaluated = BinaryHeap::new();
+
+        if max_qty == 0 {
+            return evaluated;
+        }
+
+        pending.push(HeapElement {
+            distance: A::zero(),
+            element: self,
+        });
+
+        while !pending.is_empty() {
+            self.best_n_within_step(
+                point,
+                max_qty,
+                radius,
+                metric,
+                key,
+                &mut pending,
+                &mut evaluated,
+            );
+        }
+
+        evaluated
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn best_n_within_step<'b, F, G, R>(
+        &self,
+        point: &[A; K],
+        max_qty: usize,
+        max_dist: A,
+        metric: &Metric<A, F, K>,
+        key: &G,
+        pending: &mut Vec<HeapElement<A, &'b Self>>,
+        evaluated: &mut BinaryHeap<RankedElement<R, PointResult<'b, A, T, K>>>,
+    ) where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        G: Fn(&'b T) -> R,
+        R: Ord,
+    {
+        let curr = &mut &*pending.pop().unwrap().element;
+        Self::populate_pending(point, max_dist, metric, pending, curr);
+
+        match &curr.content {
+            Node::Leaf { points, bucket, .. } => {
+                metric.record(|stats| stats.leaves_visited += 1);
+                for (p, d) in points.iter().zip(bucket.iter()) {
+                    let dist = metric.distance(point, p);
+                    if dist <= max_dist {
+                        let element = RankedElement {
+                            rank: key(d),
+                            element: (dist, p, d),
+                        };
+
+                        if evaluated.len() < max_qty {
+                            evaluated.push(element);
+                        } else {
+                            let mut top = evaluated.peek_mut().unwrap();
+                            if element < *top {
+                                *top = element;
+                            }
+                        }
+                    }
+                }
+            }
+            Node::Stem { .. } => unreachable!(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn nearest_step<'b, F, P>(
+        &self,
+        point: &[A; K],
+        num: usize,
+        max_dist: A,
+        metric: &Metric<A, F, K>,
+        filter: &P,
+        pending: &mut BinaryHeap<HeapElement<A, &'b Self>>,
+        evaluated: &mut BinaryHeap<Candidate<'b, A, T, K>>,
+    ) where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Fn(&T) -> bool,
+    {
+        let curr = &mut &*pending.pop().unwrap().element;
+        Self::populate_pending(point, max_dist, metric, pending, curr);
+
+        match &curr.content {
+            Node::Leaf { points, bucket, .. } => {
+                metric.record(|stats| stats.leaves_visited += 1);
+                let points = points.iter();
+                let bucket = bucket.iter();
+                let iter =
+                    points
+                        .zip(bucket)
+                        .filter(|(_, d)| filter(d))
+                        .map(|(p, d)| HeapElement {
+                            distance: metric.distance(point, p),
+                            element: (p, d),
+                        });
+
+                for element in iter {
+                    if element <= max_dist {
+                        if evaluated.len() < num {
+                            evaluated.push(element);
+                        } else {
+                            let mut top = evaluated.peek_mut().unwrap();
+                            if element < *top {
+                                *top = element;
+                            }
+                        }
+                    }
+                }
+            }
+            Node::Stem { .. } => unreachable!(),
+        }
+    }
+
+    fn nearest_one_step<'b, F, P>(
+        &self,
+        point: &[A; K],
+        metric: &Metric<A, F, K>,
+        filter: &P,
+        pending: &mut Vec<HeapElement<A, &'b Self>>,
+        best_dist: &mut A,
+        best_elem: &mut Option<&'b T>,
+    ) where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Fn(&T) -> bool,
+    {
+        let curr = &mut &*pending.pop().unwrap().element;
+        let evaluated_dist = *best_dist;
+        Self::populate_pending(point, evaluated_dist, metric, pending, curr);
+
+        match &curr.content {
+            Node::Leaf { points, bucket, .. } => {
+                metric.record(|stats| stats.leaves_visited += 1);
+                let points = points.iter();
+                let bucket = bucket.iter();
+                let iter =
+                    points
+                        .zip(bucket)
+                        .filter(|(_, d)| filter(d))
+                        .map(|(p, d)| HeapElement {
+                            distance: metric.distance(point, p),
+                            element: d,
+                        });
+
+                for element in iter {
+                    if best_elem.is_none() || element < *best_dist {
+                        *best_elem = Some(element.element);
+                        *best_dist = element.distance;
+                    }
+                }
+            }
+            Node::Stem { .. } => unreachable!(),
+        }
+    }
+
+    fn nearest_n_recurse<'b, F, const N: usize>(
+        &'b self,
+        point: &[A; K],
+        metric: &Metric<A, F, K>,
+        best: &mut [(A, Option<&'b T>); N],
+    ) where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        match &self.content {
+            Node::Leaf { points, bucket, .. } => {
+                metric.record(|stats| stats.leaves_visited += 1);
+                for (p, d) in points.iter().zip(bucket.iter()) {
+                    let dist = metric.distance(point, p);
+                    let worst = &mut best[N - 1];
+                    if worst.1.is_none() || dist < worst.0 {
+                        *worst = (dist, Some(d));
+
+                        // bubble the new element up into its sorted position
+                        let mut idx = N - 1;
+                        while idx > 0 && (best[idx - 1].1.is_none() || dist < best[idx - 1].0) {
+                            best.swap(idx, idx - 1);
+                            idx -= 1;
+                        }
+                    }
+                }
+            }
+            Node::Stem { left, right, .. } => {
+                metric.record(|stats| stats.stems_visited += 1);
+                let (nearer, further) = if self.belongs_in_left(point) {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+
+                nearer.nearest_n_recurse(point, metric, best);
+
+                let worst = best[N - 1];
+                if worst.1.is_none()
+                    || metric.distance_to_space(point, &further.min_bounds, &further.max_bounds)
+                        < worst.0
+                {
+                    further.nearest_n_recurse(point, metric, best);
+                } else {
+                    metric.record(|stats| stats.pruned_subtrees += 1);
+                }
+            }
+        }
+    }
+
+    fn populate_pending<'a, F>(
+        point: &[A; K],
+        max_dist: A,
+        metric: &Metric<A, F, K>,
+        pending: &mut impl Stack<HeapElement<A, &'a Self>>,
+        curr: &mut &'a Self,
+    ) where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        while let Node::Stem { left, right, .. } = &curr.content {
+            metric.record(|stats| stats.stems_visited += 1);
+            let candidate;
+            if curr.belongs_in_left(point) {
+                candidate = right;
+                *curr = left;
+            } else {
+                candidate = left;
+                *curr = right;
+            };
+
+            let candidate_to_space =
+                metric.distance_to_space(point, &candidate.min_bounds, &candidate.max_bounds);
+
+            if candidate_to_space <= max_dist {
+                pending.stack_push(HeapElement {
+                    distance: candidate_to_space * -A::one(),
+                    element: &**candidate,
+                });
+            } else {
+                metric.record(|stats| stats.pruned_subtrees += 1);
+            }
+        }
+    }
+
+    /// Returns an iterator over all elements in the tree, sorted nearest-first to the query point.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let mut nearest_iter = tree.iter_nearest(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// let nearest_first = nearest_iter.next().unwrap();
+    ///
+    /// assert!((nearest_first.0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest_first.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn iter_nearest<'a, 'b, F>(
+        &'b self,
+        point: &'a [A; K],
+        distance: &'a F,
+    ) -> Result<NearestIter<'a, 'b, A, T, F, K, O>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.iter_nearest_impl(point, self.metric(distance))
+    }
+
+    /// Returns an iterator over all elements in the tree, sorted nearest-first to the query point.
+    /// Obeys periodic boundary conditions, as described for `nearest_periodic()`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// const PERIODIC: [f64; 3] = [10.0, 10.0, 10.0];
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[9.0, 2.0, 5.0], 101)?;
+    ///
+    /// let mut nearest_iter =
+    ///     tree.iter_nearest_periodic(&[0.5, 2.0, 5.0], &squared_euclidean, &PERIODIC)?;
+    ///
+    /// assert_eq!(nearest_iter.next(), Some((0.25, &100)));
+    /// assert_eq!(nearest_iter.next(), Some((2.25, &101)));
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn iter_nearest_periodic<'a, 'b, F, P>(
+        &'b self,
+        point: &'a [A; K],
+        distance: &'a F,
+        periodic: &P,
+    ) -> Result<NearestIter<'a, 'b, A, T, F, K, O>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+        P: Into<PeriodicBox<A, K>> + Copy,
+    {
+        let metric = self.periodic_metric(point, distance, (*periodic).into())?;
+
+        self.iter_nearest_impl(point, metric)
+    }
+
+    fn iter_nearest_impl<'a, 'b, F>(
+        &'b self,
+        point: &'a [A; K],
+        metric: Metric<'a, A, F, K>,
+    ) -> Result<NearestIter<'a, 'b, A, T, F, K, O>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        metric.check_point(point, Operation::Query)?;
+
+        let mut pending = BinaryHeap::new();
+        let evaluated = BinaryHeap::<Candidate<A, T, K>>::new();
+
+        pending.push(HeapElement {
+            distance: A::zero(),
+            element: self,
+        });
+
+        Ok(NearestIter {
+            point,
+            pending,
+            evaluated,
+            metric,
+        })
+    }
+
+    /// Returns an iterator over all elements in the tree, sorted nearest-first to the query point.
+    /// Each item also contains the coordinates at which the element is stored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let mut nearest_iter = tree.iter_nearest_with_point(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// let nearest_first = nearest_iter.next().unwrap();
+    ///
+    /// assert!((nearest_first.0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest_first.1, [1.0, 2.0, 5.0]);
+    /// assert_eq!(*nearest_first.2, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn iter_nearest_with_point<'a, 'b, F>(
+        &'b self,
+        point: &'a [A; K],
+        distance: &'a F,
+    ) -> Result<NearestWithPointIter<'a, 'b, A, T, F, K, O>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.iter_nearest(point, distance).map(NearestWithPointIter)
+    }
+
+    /// Finds the nearest `num` neighbours of every element in the tree, using the specified
+    /// distance metric function. An element is never returned as a neighbour of itself,
+    /// although other elements stored at the same point are.
+    ///
+    /// The outer `Vec` contains one entry per element, in the order in which `iter()`
+    /// returns them. Each entry is sorted nearest-first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let graph = tree.knn_graph(1, &squared_euclidean)?;
+    ///
+    /// assert_eq!(graph.len(), 3);
+    /// assert_eq!(*graph[0][0].1, 101);
+    /// assert_eq!(*graph[1][0].1, 100);
+    /// assert_eq!(*graph[2][0].1, 101);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn knn_graph<F>(&self, num: usize, distance: &F) -> Result<Vec<Vec<(A, &T)>>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let metric = self.metric(distance);
+        let mut pending = BinaryHeap::new();
+        let mut evaluated = BinaryHeap::new();
+
+        self.iter()
+            .map(|(p, d)| self.knn_of(p, d, num, &metric, &mut pending, &mut evaluated))
+            .collect()
+    }
+
+    /// Finds the nearest `num` neighbours of every element in the tree, using the specified
+    /// distance metric function, in parallel. Returns the same results as `knn_graph()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let graph = tree.par_knn_graph(1, &squared_euclidean)?;
+    ///
+    /// assert_eq!(graph, tree.knn_graph(1, &squared_euclidean)?);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    #[cfg(feature = "parallel")]
+    #[allow(clippy::type_complexity)]
+    pub fn par_knn_graph<F>(&self, num: usize, distance: &F) -> Result<Vec<Vec<(A, &T)>>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A + Sync,
+        A: Send + Sync,
+        T: Sync,
+        Self: Sync,
+    {
+        self.iter()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map_init(
+                || (BinaryHeap::new(), BinaryHeap::new()),
+                |(pending, evaluated), (p, d)| {
+                    let metric = self.metric(distance);
+                    self.knn_of(p, d, num, &metric, pending, evaluated)
+                },
+            )
+            .collect()
+    }
+
+    fn knn_of<'t, F>(
+        &'t self,
+        point: &'t [A; K],
+        item: &'t T,
+        num: usize,
+        metric: &Metric<A, F, K>,
+        pending: &mut BinaryHeap<HeapElement<A, &'t Self>>,
+        evaluated: &mut BinaryHeap<Candidate<'t, A, T, K>>,
+    ) -> Result<Vec<(A, &'t T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.nearest_search(
+            point,
+            num,
+            A::infinity(),
+            metric,
+            &|other| !core::ptr::eq(other, item),
+            pending,
+            evaluated,
+        )?;
+
+        let mut neighbours = Vec::with_capacity(evaluated.len());
+        while let Some(x) = evaluated.pop() {
+            neighbours.push((x.distance, x.element.1));
+        }
+        neighbours.reverse();
+
+        Ok(neighbours)
+    }
+
+    /// Finds, for every element of `other`, the nearest `num` elements of this tree, using
+    /// the specified distance metric function. Rather than querying this tree once per
+    /// element of `other`, both trees are traversed together so that whole pairs of nodes
+    /// can be pruned using their bounding boxes.
+    ///
+    /// The result contains one entry per element of `other`, pairing it with its neighbours
+    /// sorted nearest-first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut catalogue: KdTree<f64, usize, 3> = KdTree::new();
+    /// catalogue.add(&[1.0, 2.0, 5.0], 100)?;
+    /// catalogue.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let mut observations: KdTree<f64, &str, 3> = KdTree::new();
+    /// observations.add(&[1.0, 2.0, 5.1], "a")?;
+    /// observations.add(&[2.0, 3.0, 6.1], "b")?;
+    ///
+    /// let matches = catalogue.join_nearest(&observations, 1, &squared_euclidean)?;
+    ///
+    /// assert_eq!(matches.len(), 2);
+    /// assert_eq!((*matches[0].0, *matches[0].1[0].1), ("a", 100));
+    /// assert_eq!((*matches[1].0, *matches[1].1[0].1), ("b", 101));
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn join_nearest<'a, 'b, U, F>(
+        &'a self,
+        other: &'b KdTree<A, U, K>,
+        num: usize,
+        distance: &F,
+    ) -> Result<Vec<(&'b U, Vec<(A, &'a T)>)>, ErrorKind>
+    where
+        U: PartialEq,
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.join_impl(other, num, A::infinity(), &self.metric(distance))
+    }
+
+    /// Finds, for every element of `other`, all elements of this tree within `radius` of it,
+    /// using the specified distance metric function. Both trees are traversed together, as
+    /// in `join_nearest()`.
+    ///
+    /// The result contains one entry per element of `other`, pairing it with its matches
+    /// sorted nearest-first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut catalogue: KdTree<f64, usize, 3> = KdTree::new();
+    /// catalogue.add(&[1.0, 2.0, 5.0], 100)?;
+    /// catalogue.add(&[2.0, 3.0, 6.0], 101)?;
+    /// catalogue.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let mut observations: KdTree<f64, &str, 3> = KdTree::new();
+    /// observations.add(&[1.0, 2.0, 5.0], "a")?;
+    ///
+    /// let matches = catalogue.join_within(&observations, 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].1.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn join_within<'a, 'b, U, F>(
+        &'a self,
+        other: &'b KdTree<A, U, K>,
+        radius: A,
+        distance: &F,
+    ) -> Result<Vec<(&'b U, Vec<(A, &'a T)>)>, ErrorKind>
+    where
+        U: PartialEq,
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.join_impl(other, usize::MAX, radius, &self.metric(distance))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn join_impl<'a, 'b, U, F>(
+        &'a self,
+        other: &'b KdTree<A, U, K>,
+        num: usize,
+        max_dist: A,
+        metric: &Metric<A, F, K>,
+    ) -> Result<Vec<(&'b U, Vec<(A, &'a T)>)>, ErrorKind>
+    where
+        U: PartialEq,
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let mut state = JoinState {
+            nodes: Vec::new(),
+            queries: Vec::with_capacity(other.size),
+            evaluated: Vec::new(),
+            num,
+            max_dist,
+        };
+        JoinNode::build(other, &mut state.nodes, &mut state.queries);
+
+        for (point, _) in state.queries.iter() {
+            self.check_point(point, Operation::Query)?;
+        }
+        state
+            .evaluated
+            .resize_with(state.queries.len(), BinaryHeap::new);
+
+        if self.size > 0 && num > 0 && !state.nodes.is_empty() {
+            self.join_recurse(0, &mut state, metric);
+        }
+
+        Ok(state
+            .queries
+            .iter()
+            .zip(state.evaluated)
+            .map(|((_, item), evaluated)| {
+                let neighbours = evaluated
+                    .into_sorted_vec()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect();
+                (*item, neighbours)
+            })
+            .collect())
+    }
+
+    fn join_recurse<'a, 'b, U, F>(
+        &'a self,
+        node_idx: usize,
+        state: &mut JoinState<'a, 'b, A, T, U, K>,
+        metric: &Metric<A, F, K>,
+    ) where
+        U: PartialEq,
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let node = &state.nodes[node_idx];
+        if self.distance_to_tree(node.tree, metric) > node.bound {
+            return;
+        }
+
+        // The query side is always split first, so that each leaf of queries walks this
+        // tree nearest-first. Splitting both sides together prunes far less, as a node's
+        // bound only becomes finite once every query beneath it has `num` matches.
+        match (&self.content, node.children) {
+            (_, Some((left_idx, right_idx))) => {
+                self.join_recurse(left_idx, state, metric);
+                self.join_recurse(right_idx, state, metric);
+                state.nodes[node_idx].bound = state.nodes[left_idx]
+                    .bound
+                    .max(state.nodes[right_idx].bound);
+            }
+            (Node::Leaf { points, bucket, .. }, None) => {
+                let JoinState {
+                    nodes,
+                    queries,
+                    evaluated,
+                    num,
+                    max_dist,
+                } = state;
+
+                let mut bound = A::neg_infinity();
+                for idx in nodes[node_idx].queries.clone() {
+                    let query = queries[idx].0;
+                    let evaluated = &mut evaluated[idx];
+                    let query_bound = Self::join_bound(evaluated, *num, *max_dist);
+
+                    if metric.distance_to_space(query, &self.min_bounds, &self.max_bounds)
+                        <= query_bound
+                    {
+                        for (p, d) in points.iter().zip(bucket.iter()) {
+                            let element = HeapElement {
+                                distance: metric.distance(query, p),
+                                element: d,
+                            };
+
+                            if element <= *max_dist {
+                                if evaluated.len() < *num {
+                                    evaluated.push(element);
+                                } else {
+                                    let mut top = evaluated.peek_mut().unwrap();
+                                    if element < *top {
+                                        *top = element;
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    bound = bound.max(Self::join_bound(evaluated, *num, *max_dist));
+                }
+                nodes[node_idx].bound = bound;
+            }
+            (Node::Stem { left, right, .. }, None) => {
+                let (nearer, further) = Self::order_by_distance(left, right, node.tree, metric);
+                nearer.join_recurse(node_idx, state, metric);
+                further.join_recurse(node_idx, state, metric);
+            }
+        }
+    }
+
+    // The distance beyond which no further element can improve on `evaluated`
+    fn join_bound(evaluated: &BinaryHeap<HeapElement<A, &T>>, num: usize, max_dist: A) -> A {
+        if evaluated.len() < num {
+            max_dist
+        } else {
+            evaluated.peek().unwrap().distance
+        }
+    }
+
+    fn order_by_distance<'a, U, F>(
+        left: &'a Self,
+        right: &'a Self,
+        other: &KdTree<A, U, K>,
+        metric: &Metric<A, F, K>,
+    ) -> (&'a Self, &'a Self)
+    where
+        U: PartialEq,
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if left.distance_to_tree(other, metric) <= right.distance_to_tree(other, metric) {
+            (left, right)
+        } else {
+            (right, left)
+        }
+    }
+
+    fn distance_to_tree<U, F>(&self, other: &KdTree<A, U, K>, metric: &Metric<A, F, K>) -> A
+    where
+        U: PartialEq,
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        metric.distance_between_spaces(
+            &self.min_bounds,
+            &self.max_bounds,
+            &other.min_bounds,
+            &other.max_bounds,
+        )
+    }
+
+    pub(crate) fn add_to_bucket(&mut self, point: &[A; K], data: T) {
+        self.extend_bounds(point);
+        let cap = match &mut self.content {
+            Node::Leaf {
+                ref mut points,
+                ref mut bucket,
+                capacity,
+            } => {
+                points.push(*point);
+                bucket.push(data);
+                *capacity
+            }
+            Node::Stem { .. } => unreachable!(),
+        };
+
+        self.size += 1;
+        if self.size > cap {
+            self.split();
+        }
+    }
+
+    fn split(&mut self) {
+        match &mut self.content {
+            Node::Leaf {
+                ref mut bucket,
+                ref mut points,
+                capacity,
+                ..
+            } => {
+                if let Some((split_dimension, split_value)) =
+                    util::split_plane(&self.min_bounds, &self.max_bounds)
+                {
+                    let mut left = Self::empty(*capacity, self.periodic).unwrap();
+                    let mut right = Self::empty(*capacity, self.periodic).unwrap();
+
+                    while !points.is_empty() {
+                        let point = points.swap_remove(0);
+                        let data = bucket.swap_remove(0);
+                        if point[split_dimension] < split_value {
+                            // belongs_in_left
+                            left.add_to_bucket(&point, data);
+                        } else {
+                            right.add_to_bucket(&point, data);
+                        }
+                    }
+
+                    self.content = Node::Stem {
+                        left: O::new_child(left),
+                        right: O::new_child(right),
+                        split_value,
+                        split_dimension: split_dimension as u8,
+                    }
+                }
+            }
+            Node::Stem { .. } => unreachable!(),
+        }
+    }
+
+    // Builds a tree holding `points` and `bucket`, checking the capacity, periodic box
+    // and points in the same way as creating a tree and adding them to it would
+    #[cfg(any(feature = "serialize", feature = "io"))]
+    pub(crate) fn from_elements(
+        capacity: usize,
+        periodic: Option<PeriodicBox<A, K>>,
+        mut points: Vec<[A; K]>,
+        bucket: Vec<T>,
+    ) -> Result<Self, ErrorKind> {
+        if capacity == 0 {
+            return Err(ErrorKind::ZeroCapacity);
+        }
+        if let Some(periodic) = &periodic {
+            periodic.validate()?;
+        }
+
+        for point in points.iter_mut() {
+            *point = util::wrap_point(point, periodic.as_ref());
+            util::check_point(point, periodic.as_ref(), Operation::Add)?;
+        }
+
+        Ok(Self::build(capacity, periodic, points, bucket))
+    }
+
+    // Builds a tree holding `points` and `bucket` top-down, splitting each node in the
+    // same way as `split` but without adding the elements one at a time
+    pub(crate) fn build(
+        capacity: usize,
+        periodic: Option<PeriodicBox<A, K>>,
+        points: Vec<[A; K]>,
+        bucket: Vec<T>,
+    ) -> Self {
+        let mut tree = KdTree {
+            size: points.len(),
+            min_bounds: [A::infinity(); K],
+            max_bounds: [A::neg_infinity(); K],
+            content: Node::Leaf {
+                points: Vec::new(),
+                bucket: Vec::new(),
+                capacity,
+            },
+            periodic,
+        };
+        for point in &points {
+            tree.extend_bounds(point);
+        }
+
+        let split = if tree.size > capacity {
+            util::split_plane(&tree.min_bounds, &tree.max_bounds)
+        } else {
+            None
+        };
+
+        tree.content = match split {
+            Some((split_dimension, split_value)) => {
+                let (mut left_points, mut left_bucket) = (Vec::new(), Vec::new());
+                let (mut right_points, mut right_bucket) = (Vec::new(), Vec::new());
+                for (point, data) in points.into_iter().zip(bucket) {
+                    if point[split_dimension] < split_value {
+                        left_points.push(point);
+                        left_bucket.push(data);
+                    } else {
+                        right_points.push(point);
+                        right_bucket.push(data);
+                    }
+                }
+
+                Node::Stem {
+                    left: O::new_child(Self::build(capacity, periodic, left_points, left_bucket)),
+                    right: O::new_child(Self::build(
+                        capacity,
+                        periodic,
+                        right_points,
+                        right_bucket,
+                    )),
+                    split_value,
+                    split_dimension: split_dimension as u8,
+                }
+            }
+            None => Node::Leaf {
+                points,
+                bucket,
+                capacity,
+            },
+        };
+
+        tree
+    }
+
+    // Returns the capacity per node that the tree was created with
+    pub(crate) fn capacity(&self) -> usize {
+        let mut curr = self;
+        loop {
+            match &curr.content {
+                Node::Leaf { capacity, .. } => return *capacity,
+                Node::Stem { left, .. } => curr = left,
+            }
+        }
+    }
+
+    fn belongs_in_left(&self, point: &[A; K]) -> bool {
+        match &self.content {
+            Node::Stem {
+                ref split_dimension,
+                ref split_value,
+                ..
+            } => point[*split_dimension as usize] < *split_value,
+            Node::Leaf { .. } => unreachable!(),
+        }
+    }
+
+    pub(crate) fn extend_bounds(&mut self, point: &[A; K]) {
+        let min = self.min_bounds.iter_mut();
+        let max = self.max_bounds.iter_mut();
+        for ((l, h), v) in min.zip(max).zip(point.iter()) {
+            if v < l {
+                *l = *v
+            }
+            if v > h {
+                *h = *v
+            }
+        }
+    }
+
+    pub(crate) fn check_point(
+        &self,
+        point: &[A; K],
+        operation: Operation,
+    ) -> Result<(), ErrorKind> {
+        util::check_point(point, self.periodic.as_ref(), operation)
+    }
+
+    // Wraps `point` back into the periodic box, if the tree is configured to do so
+    pub(crate) fn wrap_point(&self, point: &[A; K]) -> [A; K] {
+        util::wrap_point(point, self.periodic.as_ref())
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize> KdTree<A, T, K> {
+    /// Returns an iterator over every element in the tree that allows each element to be
+    /// modified. The coordinates at which elements are stored cannot be changed, as that
+    /// would invalidate the tree's structure. Elements are visited in the same order as
+    /// by `iter()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// for (_, item) in tree.iter_mut() {
+    ///     *item += 10;
+    /// }
+    ///
+    /// assert_eq!(tree.iter().map(|(_, &item)| item).sum::<usize>(), 221);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, A, T, K> {
+        let remaining = self.size;
+
+        IterMut {
+            stack: vec![self],
+            leaf: [].iter().zip([].iter_mut()),
+            remaining,
+        }
+    }
+
+    /// Queries the tree to find the nearest element to `point`, using the specified
+    /// distance metric function, and returns a mutable reference to it. The coordinates at
+    /// which the element is stored cannot be changed this way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let nearest = tree.nearest_one_mut(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    /// *nearest.1 += 1000;
+    ///
+    /// assert_eq!(*tree.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?.1, 1100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_one_mut<F>(
+        &mut self,
+        point: &[A; K],
+        distance: &F,
+    ) -> Result<(A, &mut T), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if self.size == 0 {
+            return Err(ErrorKind::Empty);
+        }
+        self.check_point(point, Operation::Query)?;
+
+        let metric = self.metric(distance);
+        let mut best_dist: A = A::infinity();
+        let mut best_elem: Option<&mut T> = None;
+        let mut pending = BinaryHeap::new();
+
+        pending.push(HeapElement {
+            distance: A::zero(),
+            element: self,
+        });
+
+        // Nodes are visited nearest-first, so the search ends at the first node that is
+        // no nearer than the best element found so far
+        while let Some(HeapElement {
+            distance,
+            element: node,
+        }) = pending.pop()
+        {
+            if best_elem.is_some() && -distance >= best_dist {
+                break;
+            }
+
+            match &mut node.content {
+                Node::Leaf { points, bucket, .. } => {
+                    for (p, d) in points.iter().zip(bucket.iter_mut()) {
+                        let distance = metric.distance(point, p);
+                        if best_elem.is_none() || distance < best_dist {
+                            best_dist = distance;
+                            best_elem = Some(d);
+                        }
+                    }
+                }
+                Node::Stem { left, right, .. } => {
+                    for child in [left, right] {
+                        let distance =
+                            metric.distance_to_space(point, &child.min_bounds, &child.max_bounds);
+                        if best_elem.is_none() || distance < best_dist {
+                            pending.push(HeapElement {
+                                distance: -distance,
+                                element: &mut **child,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        best_elem
+            .map(|elem| (best_dist, elem))
+            .ok_or(ErrorKind::Empty)
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function, and returns mutable references to them. Results are
+    /// returned sorted nearest-first. The coordinates at which the elements are stored
+    /// cannot be changed this way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// for (_, item) in tree.within_mut(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)? {
+    ///     *item = 0;
+    /// }
+    ///
+    /// assert_eq!(tree.iter().map(|(_, &item)| item).sum::<usize>(), 102);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within_mut<F>(
+        &mut self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<Vec<(A, &mut T)>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        if self.size == 0 {
+            return Ok(vec![]);
+        }
+        self.check_point(point, Operation::Query)?;
+
+        let metric = self.metric(distance);
+        let mut evaluated = Vec::new();
+        let mut pending = vec![self];
+
+        while let Some(node) = pending.pop() {
+            if metric.distance_to_space(point, &node.min_bounds, &node.max_bounds) > radius {
+                continue;
+            }
+
+            match &mut node.content {
+                Node::Leaf { points, bucket, .. } => {
+                    for (p, d) in points.iter().zip(bucket.iter_mut()) {
+                        let distance = metric.distance(point, p);
+                        if distance <= radius {
+                            evaluated.push(HeapElement {
+                                distance,
+                                element: d,
+                            });
+                        }
+                    }
+                }
+                Node::Stem { left, right, .. } => {
+                    pending.push(right);
+                    pending.push(left);
+                }
             }
         }
+
+        evaluated.sort();
+
+        Ok(evaluated.into_iter().map(Into::into).collect())
     }
 
-    /// Returns an iterator over all elements in the tree, sorted nearest-first to the query point.
+    /// Returns a [`Searcher`] that can perform repeated queries against this tree,
+    /// reusing its internal buffers between queries rather than allocating afresh each time.
     ///
     /// # Examples
     ///
@@ -1369,39 +2877,14 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// tree.add(&[1.0, 2.0, 5.0], 100)?;
     /// tree.add(&[2.0, 3.0, 6.0], 101)?;
     ///
-    /// let mut nearest_iter = tree.iter_nearest(&[1.0, 2.0, 5.1], &squared_euclidean)?;
-    ///
-    /// let nearest_first = nearest_iter.next().unwrap();
+    /// let mut searcher = tree.searcher();
+    /// let nearest = searcher.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?;
     ///
-    /// assert!((nearest_first.0 - 0.01f64).abs() < f64::EPSILON);
-    /// assert_eq!(*nearest_first.1, 100);
+    /// assert_eq!(*nearest.1, 100);
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
-    pub fn iter_nearest<'a, 'b, F>(
-        &'b self,
-        point: &'a [A; K],
-        distance: &'a F,
-    ) -> Result<NearestIter<'a, 'b, A, T, F, K>, ErrorKind>
-    where
-        F: Fn(&[A; K], &[A; K]) -> A,
-    {
-        self.check_point(point)?;
-
-        let mut pending = BinaryHeap::new();
-        let evaluated = BinaryHeap::<HeapElement<A, &T>>::new();
-
-        pending.push(HeapElement {
-            distance: A::zero(),
-            element: self,
-        });
-
-        Ok(NearestIter {
-            point,
-            pending,
-            evaluated,
-            distance,
-            periodic: self.periodic
-        })
+    pub fn searcher(&self) -> Searcher<'_, A, T, K> {
+        Searcher::new(self)
     }
 
     /// Add an element to the tree. The first argument specifies the location in kd space
@@ -1422,15 +2905,17 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
     /// # Ok::<(), kiddo::ErrorKind>(())
     /// ```
     pub fn add(&mut self, point: &[A; K], data: T) -> Result<(), ErrorKind> {
-        self.check_point(point)?;
-        self.add_unchecked(point, data)
+        let point = self.wrap_point(point);
+        self.check_point(&point, Operation::Add)?;
+        self.add_unchecked(&point, data);
+        Ok(())
     }
 
-    fn add_unchecked(&mut self, point: &[A; K], data: T) -> Result<(), ErrorKind> {
-        let res = match &mut self.content {
+    fn add_unchecked(&mut self, point: &[A; K], data: T) {
+        match &mut self.content {
             Node::Leaf { .. } => {
                 self.add_to_bucket(point, data);
-                return Ok(());
+                return;
             }
 
             Node::Stem {
@@ -1446,39 +2931,16 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
                     right.add_unchecked(point, data)
                 }
             }
-        };
-
-        self.extend(point);
-        self.size += 1;
-
-        res
-    }
-
-    fn add_to_bucket(&mut self, point: &[A; K], data: T) {
-        self.extend(point);
-        let cap;
-        match &mut self.content {
-            Node::Leaf {
-                ref mut points,
-                ref mut bucket,
-                capacity,
-            } => {
-                points.push(*point);
-                bucket.push(data);
-                cap = *capacity;
-            }
-            Node::Stem { .. } => unreachable!(),
         }
 
+        self.extend_bounds(point);
         self.size += 1;
-        if self.size > cap {
-            self.split();
-        }
     }
 
     pub fn remove(&mut self, point: &[A; K], data: &T) -> Result<usize, ErrorKind> {
         let mut removed = 0;
-        self.check_point(point)?;
+        let point = &self.wrap_point(point);
+        self.check_point(point, Operation::Remove)?;
 
         match &mut self.content {
             Node::Leaf {
@@ -1520,120 +2982,222 @@ impl<A: Float + Zero + One + Signed, T: std::cmp::PartialEq, const K: usize> KdT
         Ok(removed)
     }
 
-    fn split(&mut self) {
-        match &mut self.content {
-            Node::Leaf {
-                ref mut bucket,
-                ref mut points,
-                capacity,
-                ..
-            } => {
-                let mut split_dimension: Option<usize> = None;
-                let mut max = A::zero();
-                for dim in 0..K {
-                    let diff = self.max_bounds[dim] - self.min_bounds[dim];
-                    if !diff.is_nan() && diff > max {
-                        max = diff;
-                        split_dimension = Some(dim);
-                    }
-                }
-
-                if let Some(split_dimension) = split_dimension {
-                    let min = self.min_bounds[split_dimension];
-                    let max = self.max_bounds[split_dimension];
-                    let split_value = min + (max - min) / A::from(2.0_f64).unwrap();
-
-                    let mut left;
-                    let mut right;
-                    if self.periodic.is_some() {
-                       left = Box::new(KdTree::periodic_with_per_node_capacity(*capacity, self.periodic.unwrap()).unwrap());
-                       right = Box::new(KdTree::periodic_with_per_node_capacity(*capacity, self.periodic.unwrap()).unwrap());
-                    } else {
-                       left = Box::new(KdTree::with_per_node_capacity(*capacity).unwrap());
-                       right = Box::new(KdTree::with_per_node_capacity(*capacity).unwrap());
-                    }
-
-
-                    while !points.is_empty() {
-                        let point = points.swap_remove(0);
-                        let data = bucket.swap_remove(0);
-                        if point[split_dimension] < split_value {
-                            // belongs_in_left
-                            left.add_to_bucket(&point, data);
-                        } else {
-                            right.add_to_bucket(&point, data);
-                        }
-                    }
+    /// Combines this tree with `other`, returning a tree that holds the elements of both.
+    /// The result has the same capacity per node and periodic boundary conditions as this
+    /// tree, and elements of `other` are checked and wrapped as if they were added to it.
+    ///
+    /// If `other` is small compared to this tree, its elements are added to this tree one
+    /// at a time. Otherwise the combined tree is rebuilt from scratch, which is quicker
+    /// than adding every element and keeps the tree balanced.
+    ///
+    /// Returns an error if any element of `other` could not be added to this tree. Nothing
+    /// is merged in that case, and both trees are handed back unchanged alongside the
+    /// error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree_1: KdTree<f64, usize, 3> = KdTree::new();
+    /// let mut tree_2: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree_1.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree_2.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let tree = tree_1.merge(tree_2).map_err(|(error, _, _)| error)?;
+    ///
+    /// assert_eq!(tree.size(), 2);
+    /// assert_eq!(*tree.nearest_one(&[2.0, 3.0, 6.1], &squared_euclidean)?.1, 101);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    #[allow(clippy::result_large_err)]
+    pub fn merge(mut self, other: Self) -> Result<Self, (ErrorKind, Self, Self)> {
+        let incoming = other
+            .points()
+            .map(|point| {
+                let point = self.wrap_point(point);
+                self.check_point(&point, Operation::Add).map(|_| point)
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let incoming = match incoming {
+            Ok(incoming) => incoming,
+            Err(error) => return Err((error, self, other)),
+        };
 
-                    self.content = Node::Stem {
-                        left,
-                        right,
-                        split_value,
-                        split_dimension: split_dimension as u8,
-                    }
-                }
+        // Adding an element costs about as much as descending the tree, so once `other`
+        // holds a sizeable fraction of this tree's elements a rebuild works out cheaper
+        if other.size < self.size / 4 {
+            for (point, (_, data)) in incoming.iter().zip(other) {
+                self.add_unchecked(point, data);
             }
-            Node::Stem { .. } => unreachable!(),
+            return Ok(self);
         }
-    }
 
-    fn belongs_in_left(&self, point: &[A; K]) -> bool {
-        match &self.content {
-            Node::Stem {
-                ref split_dimension,
-                ref split_value,
-                ..
-            } => point[*split_dimension as usize] < *split_value,
-            Node::Leaf { .. } => unreachable!(),
+        let (capacity, periodic) = (self.capacity(), self.periodic);
+        let size = self.size + other.size;
+        let (mut points, mut bucket) = (Vec::with_capacity(size), Vec::with_capacity(size));
+        for (point, data) in self {
+            points.push(point);
+            bucket.push(data);
+        }
+        for (point, (_, data)) in incoming.into_iter().zip(other) {
+            points.push(point);
+            bucket.push(data);
         }
+
+        Ok(Self::build(capacity, periodic, points, bucket))
     }
 
-    fn extend(&mut self, point: &[A; K]) {
-        let min = self.min_bounds.iter_mut();
-        let max = self.max_bounds.iter_mut();
-        for ((l, h), v) in min.zip(max).zip(point.iter()) {
-            if v < l {
-                *l = *v
-            }
-            if v > h {
-                *h = *v
+    /// Splits the tree into at most `n` independent trees, or shards. The largest shard is
+    /// split in two until there are `n` shards or no shard can be split further, which is
+    /// once every shard is a single leaf. A shard is split by detaching the subtrees at its
+    /// stem where they hold similar numbers of elements; otherwise, as on skewed data, it
+    /// is rebuilt as two trees either side of the median of its elements along its widest
+    /// axis. Either way, shards hold roughly equal numbers of elements.
+    ///
+    /// Each split puts the shard on the lower side of the split before the other, and
+    /// empty shards are discarded, so an empty tree, or `n` of zero, gives no shards at
+    /// all. Each shard's `min_bounds()` and `max_bounds()` give the region it covers,
+    /// which can be used to route queries or new elements to it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    ///
+    /// let mut tree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(2)?;
+    ///
+    /// for idx in 0..16 {
+    ///     tree.add(&[idx as f64, 0.0], idx)?;
+    /// }
+    ///
+    /// let shards = tree.partition(4);
+    ///
+    /// assert_eq!(shards.len(), 4);
+    /// assert_eq!(shards.iter().map(|shard| shard.size()).sum::<usize>(), 16);
+    /// assert!(shards[0].max_bounds()[0] < shards[1].min_bounds()[0]);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn partition(self, n: usize) -> Vec<Self> {
+        let mut shards = Vec::with_capacity(n);
+        if n > 0 && self.size > 0 {
+            shards.push(self);
+        }
+
+        while shards.len() < n {
+            let largest = shards
+                .iter()
+                .enumerate()
+                .filter(|(_, shard)| !shard.is_leaf() && shard.size > 1)
+                .max_by_key(|(_, shard)| shard.size)
+                .map(|(idx, _)| idx);
+
+            let idx = match largest {
+                Some(idx) => idx,
+                None => break,
+            };
+
+            if let Node::Stem { left, right, .. } = shards.remove(idx).content {
+                let children = Self::split_evenly(*left, *right);
+                shards.splice(
+                    idx..idx,
+                    children.into_iter().filter(|child| child.size > 0),
+                );
             }
         }
+
+        shards
     }
 
-    fn check_point(&self, point: &[A; K]) -> Result<(), ErrorKind> {
+    // Splits the elements of a stem's subtrees into two trees of roughly equal size: the
+    // subtrees themselves if neither holds more than twice as many elements as the other,
+    // and otherwise two trees rebuilt from either side of the median of their elements
+    // along the axis on which they are most spread out
+    fn split_evenly(left: Self, right: Self) -> Vec<Self> {
+        if left.size.max(right.size) <= 2 * left.size.min(right.size) {
+            return vec![left, right];
+        }
 
-        // First check that point is finite
-        if !point.iter().all(|n| n.is_finite()) {
-            return Err(ErrorKind::NonFiniteCoordinate)
+        let mut min_bounds = left.min_bounds;
+        let mut max_bounds = left.max_bounds;
+        for axis in 0..K {
+            min_bounds[axis] = min_bounds[axis].min(right.min_bounds[axis]);
+            max_bounds[axis] = max_bounds[axis].max(right.max_bounds[axis]);
         }
+        let axis = util::split_plane(&min_bounds, &max_bounds).map_or(0, |(axis, _)| axis);
+
+        let (capacity, periodic) = (left.capacity(), left.periodic);
+        let mut lower: Vec<([A; K], T)> = left.into_iter().chain(right).collect();
+        let median = lower.len() / 2;
+        lower.select_nth_unstable_by(median, |(a, _), (b, _)| {
+            a[axis]
+                .partial_cmp(&b[axis])
+                .unwrap_or(core::cmp::Ordering::Equal)
+        });
+        let upper = lower.split_off(median);
 
-        // Then check that point is in the bounds when periodic BCs are on
-        if self.periodic.is_some() {
+        vec![lower, upper]
+            .into_iter()
+            .map(|elements| {
+                let (points, bucket) = elements.into_iter().unzip();
+                Self::build(capacity, periodic, points, bucket)
+            })
+            .collect()
+    }
+}
 
-            // Used several times, so unwrap once here.
-            let periodic = self.periodic.unwrap();
+// A node of the tree whose elements are the queries of a join, along with the range
+// of those queries that it contains and the furthest distance at which any of them
+// could still find a better match.
+struct JoinNode<'b, A, U: PartialEq, const K: usize> {
+    tree: &'b KdTree<A, U, K>,
+    children: Option<(usize, usize)>,
+    queries: core::ops::Range<usize>,
+    bound: A,
+}
 
-            // Check if any component is above or below the bound specified in `periodic`
-            let (above_bound, below_bound) = periodic
-                .iter()
-                .fold((false, false), |mut acc, &x| {
-                    for idx in 0..K {
-                        acc = (acc.0 || x > periodic[idx], acc.1 || x < periodic[idx]);
-                    }
-                    acc
-                });
+impl<'b, A: Float, U: PartialEq, const K: usize> JoinNode<'b, A, U, K> {
+    fn build(
+        tree: &'b KdTree<A, U, K>,
+        nodes: &mut Vec<Self>,
+        queries: &mut Vec<(&'b [A; K], &'b U)>,
+    ) {
+        let idx = nodes.len();
+        let start = queries.len();
+        nodes.push(JoinNode {
+            tree,
+            children: None,
+            queries: start..start,
+            bound: A::infinity(),
+        });
 
-            // If so, then return 
-            if above_bound || below_bound {
-                return Err(ErrorKind::PeriodicOutOfBounds)
+        match &tree.content {
+            Node::Leaf { points, bucket, .. } => {
+                queries.extend(points.iter().zip(bucket.iter()));
+            }
+            Node::Stem { left, right, .. } => {
+                let left_idx = nodes.len();
+                JoinNode::build(left, nodes, queries);
+                let right_idx = nodes.len();
+                JoinNode::build(right, nodes, queries);
+                nodes[idx].children = Some((left_idx, right_idx));
             }
         }
 
-        Ok(())
+        nodes[idx].queries = start..queries.len();
     }
 }
 
+struct JoinState<'a, 'b, A, T, U: PartialEq, const K: usize> {
+    nodes: Vec<JoinNode<'b, A, U, K>>,
+    queries: Vec<(&'b [A; K], &'b U)>,
+    evaluated: Vec<BinaryHeap<HeapElement<A, &'a T>>>,
+    num: usize,
+    max_dist: A,
+}
+
 pub struct NearestIter<
     'a,
     'b,
@@ -1641,31 +3205,41 @@ pub struct NearestIter<
     T: 'b + PartialEq,
     F: 'a + Fn(&[A; K], &[A; K]) -> A,
     const K: usize,
+    O: Ownership = Owned,
 > {
     point: &'a [A; K],
-    pending: BinaryHeap<HeapElement<A, &'b KdTree<A, T, K>>>,
-    evaluated: BinaryHeap<HeapElement<A, &'b T>>,
-    distance: &'a F,
-    periodic: Option<[A; K]>,
+    pending: BinaryHeap<HeapElement<A, &'b KdTree<A, T, K, O>>>,
+    evaluated: BinaryHeap<Candidate<'b, A, T, K>>,
+    metric: Metric<'a, A, F, K>,
 }
 
-impl<'a, 'b, A: Float + Zero + One + Signed, T: 'b, F: 'a, const K: usize> Iterator
-    for NearestIter<'a, 'b, A, T, F, K>
+impl<'a, 'b, A: Float + Zero + One + Signed, T: 'b, F: 'a, const K: usize, O: Ownership> Iterator
+    for NearestIter<'a, 'b, A, T, F, K, O>
 where
     F: Fn(&[A; K], &[A; K]) -> A,
     T: PartialEq,
 {
     type Item = (A, &'b T);
     fn next(&mut self) -> Option<(A, &'b T)> {
-        use util::distance_to_space;
+        self.next_with_point()
+            .map(|(distance, _, element)| (distance, element))
+    }
+}
 
-        let distance = self.distance;
+impl<'a, 'b, A: Float + Zero + One + Signed, T: 'b, F: 'a, const K: usize, O: Ownership>
+    NearestIter<'a, 'b, A, T, F, K, O>
+where
+    F: Fn(&[A; K], &[A; K]) -> A,
+    T: PartialEq,
+{
+    fn next_with_point(&mut self) -> Option<(A, &'b [A; K], &'b T)> {
+        let metric = &self.metric;
         let point = self.point;
         while !self.pending.is_empty()
             && (self.evaluated.peek().map_or(A::infinity(), |x| -x.distance)
                 >= -self.pending.peek().unwrap().distance)
         {
-            let mut curr = &*self.pending.pop().unwrap().element;
+            let mut curr = self.pending.pop().unwrap().element;
             while let Node::Stem { left, right, .. } = &curr.content {
                 let candidate;
                 if curr.belongs_in_left(point) {
@@ -1676,19 +3250,15 @@ where
                     curr = right;
                 };
                 self.pending.push(HeapElement {
-                    distance: 
-                        -distance_to_space(
-                            point,
-                            &candidate.min_bounds,
-                            &candidate.max_bounds,
-                            distance,
-                        ),
+                    distance: -metric.distance_to_space(
+                        point,
+                        &candidate.min_bounds,
+                        &candidate.max_bounds,
+                    ),
                     element: &**candidate,
                 });
             }
 
-            // Local clone of periodic to satisfy borrow checker before mut borrow
-            let periodic = self.periodic.clone();
             match &curr.content {
                 Node::Leaf { points, bucket, .. } => {
                     let points = points.iter();
@@ -1696,84 +3266,262 @@ where
 
                     self.evaluated
                         .extend(points.zip(bucket).map(|(p, d)| HeapElement {
-                            distance: -get_distance(point, p, distance, periodic),
-                            element: d,
+                            distance: -metric.distance(point, p),
+                            element: (p, d),
                         }));
                 }
                 Node::Stem { .. } => unreachable!(),
             }
         }
-        self.evaluated.pop().map(|x| (-x.distance, x.element))
+        self.evaluated
+            .pop()
+            .map(|x| (-x.distance, x.element.0, x.element.1))
     }
 }
 
-pub fn get_distance<'a, 'b, A, F, const K: usize>(
-    a: &[A; K],
-    b: &[A; K],
-    distance: &F,
-    periodic: Option<[A; K]>,
-) -> A
-where
+pub struct NearestWithPointIter<
+    'a,
+    'b,
     A: 'a + 'b + Float,
+    T: 'b + PartialEq,
+    F: 'a + Fn(&[A; K], &[A; K]) -> A,
+    const K: usize,
+    O: Ownership = Owned,
+>(NearestIter<'a, 'b, A, T, F, K, O>);
+
+impl<'a, 'b, A: Float + Zero + One + Signed, T: 'b, F: 'a, const K: usize, O: Ownership> Iterator
+    for NearestWithPointIter<'a, 'b, A, T, F, K, O>
+where
     F: Fn(&[A; K], &[A; K]) -> A,
+    T: PartialEq,
 {
-    // If not using periodic boundary conditions, just calculate and return distance
-    if periodic.is_none() {
+    type Item = (A, &'b [A; K], &'b T);
+    fn next(&mut self) -> Option<(A, &'b [A; K], &'b T)> {
+        self.0.next_with_point()
+    }
+}
 
-        return distance(a, b)
+/// Adds every element yielded by `iter` to the tree, as if by `add()`.
+///
+/// Panics if any element could not be added, such as one with non-finite coordinates.
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::KdTree;
+///
+/// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+///
+/// tree.extend(vec![([1.0, 2.0, 5.0], 100), ([2.0, 3.0, 6.0], 101)]);
+///
+/// assert_eq!(tree.size(), 2);
+/// ```
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize> Extend<([A; K], T)>
+    for KdTree<A, T, K>
+{
+    fn extend<I: IntoIterator<Item = ([A; K], T)>>(&mut self, iter: I) {
+        for (point, data) in iter {
+            if let Err(err) = self.add(&point, data) {
+                panic!("{}", err);
+            }
+        }
+    }
+}
 
-    
-    // Otherwise, calculate the minimum distance from all mirror images
-    } else {
-        
-        // Initialize min to max possible distance
-        let mut min: A = periodic
-            .unwrap()
-            .iter()
-            .fold(
-                A::zero(),
-                |acc, &x| acc + x*x,
-            );
+/// An iterator over the elements of a tree and their coordinates, as returned by
+/// [`KdTree::iter`]
+pub struct Iter<'a, A, T: PartialEq, const K: usize, O: Ownership = Owned> {
+    stack: Vec<&'a KdTree<A, T, K, O>>,
+    leaf: Zip<slice::Iter<'a, [A; K]>, slice::Iter<'a, T>>,
+    remaining: usize,
+}
+
+impl<'a, A, T: PartialEq, const K: usize, O: Ownership> Iterator for Iter<'a, A, T, K, O> {
+    type Item = (&'a [A; K], &'a T);
+    fn next(&mut self) -> Option<(&'a [A; K], &'a T)> {
+        loop {
+            if let Some(entry) = self.leaf.next() {
+                self.remaining -= 1;
+                return Some(entry);
+            }
+
+            match &self.stack.pop()?.content {
+                Node::Leaf { points, bucket, .. } => {
+                    self.leaf = points.iter().zip(bucket.iter());
+                }
+                Node::Stem { left, right, .. } => {
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<A, T: PartialEq, const K: usize, O: Ownership> ExactSizeIterator for Iter<'_, A, T, K, O> {}
+
+/// An iterator over the elements of a tree and their coordinates that allows the elements
+/// to be modified, as returned by [`KdTree::iter_mut`]
+pub struct IterMut<'a, A, T: PartialEq, const K: usize> {
+    stack: Vec<&'a mut KdTree<A, T, K>>,
+    leaf: Zip<slice::Iter<'a, [A; K]>, slice::IterMut<'a, T>>,
+    remaining: usize,
+}
+
+impl<'a, A, T: PartialEq, const K: usize> Iterator for IterMut<'a, A, T, K> {
+    type Item = (&'a [A; K], &'a mut T);
+    fn next(&mut self) -> Option<(&'a [A; K], &'a mut T)> {
+        loop {
+            if let Some(entry) = self.leaf.next() {
+                self.remaining -= 1;
+                return Some(entry);
+            }
+
+            match &mut self.stack.pop()?.content {
+                Node::Leaf { points, bucket, .. } => {
+                    self.leaf = points.iter().zip(bucket.iter_mut());
+                }
+                Node::Stem { left, right, .. } => {
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<A, T: PartialEq, const K: usize> ExactSizeIterator for IterMut<'_, A, T, K> {}
+
+/// An iterator over the coordinates of the elements of a tree, as returned by
+/// [`KdTree::points`]
+pub struct Points<'a, A, T: PartialEq, const K: usize, O: Ownership = Owned>(Iter<'a, A, T, K, O>);
+
+impl<'a, A, T: PartialEq, const K: usize, O: Ownership> Iterator for Points<'a, A, T, K, O> {
+    type Item = &'a [A; K];
+    fn next(&mut self) -> Option<&'a [A; K]> {
+        self.0.next().map(|(point, _)| point)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
 
-        // Calculate distance for every image
-        for image_idx in 0..3_i32.pow(K as u32) {
+impl<A, T: PartialEq, const K: usize, O: Ownership> ExactSizeIterator for Points<'_, A, T, K, O> {}
 
-            // Initialize current_image template
-            let mut current_image: [i32; K] = [0; K];
+/// An iterator that moves the elements and their coordinates out of a tree, as returned
+/// by `KdTree::into_iter`
+pub struct IntoIter<A, T: PartialEq, const K: usize> {
+    stack: Vec<KdTree<A, T, K>>,
+    leaf: Zip<vec::IntoIter<[A; K]>, vec::IntoIter<T>>,
+    remaining: usize,
+}
 
-            // Calculate current image
-            for idx in 0..K {
-                current_image[idx] = (( image_idx / 3_i32.pow(idx as u32)) % 3) - 1;
+impl<A, T: PartialEq, const K: usize> Iterator for IntoIter<A, T, K> {
+    type Item = ([A; K], T);
+    fn next(&mut self) -> Option<([A; K], T)> {
+        loop {
+            if let Some(entry) = self.leaf.next() {
+                self.remaining -= 1;
+                return Some(entry);
             }
 
-            // Construct current image position
-            let mut new_a: [A; K] = a.clone();
-            for idx in 0..K {
-                new_a[idx] = new_a[idx] + A::from(current_image[idx]).unwrap()*periodic.unwrap()[idx];
+            match self.stack.pop()?.content {
+                Node::Leaf { points, bucket, .. } => {
+                    self.leaf = points.into_iter().zip(bucket);
+                }
+                Node::Stem { left, right, .. } => {
+                    self.stack.push(*right);
+                    self.stack.push(*left);
+                }
             }
+        }
+    }
 
-            // Calculate distance for this image
-            let image_distance = distance(&new_a, b);
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
 
-            // Compare with current min
-            min = min.min(image_distance);
+impl<A, T: PartialEq, const K: usize> ExactSizeIterator for IntoIter<A, T, K> {}
+
+/// Moves every element out of the tree, together with the coordinates at which it was
+/// stored, in the same order as `iter()`
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::KdTree;
+///
+/// let mut tree: KdTree<f64, String, 3> = KdTree::new();
+///
+/// tree.add(&[1.0, 2.0, 5.0], "a".to_string())?;
+/// tree.add(&[2.0, 3.0, 6.0], "b".to_string())?;
+///
+/// let entries: Vec<_> = tree.into_iter().collect();
+///
+/// assert_eq!(entries[1], ([2.0, 3.0, 6.0], "b".to_string()));
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+impl<A, T: PartialEq, const K: usize> IntoIterator for KdTree<A, T, K> {
+    type Item = ([A; K], T);
+    type IntoIter = IntoIter<A, T, K>;
+
+    fn into_iter(self) -> IntoIter<A, T, K> {
+        let remaining = self.size;
+
+        IntoIter {
+            stack: vec![self],
+            leaf: Vec::new().into_iter().zip(Vec::new()),
+            remaining,
         }
+    }
+}
+
+impl<'a, A: Float + Zero + One + Signed, T: PartialEq, const K: usize, O: Ownership> IntoIterator
+    for &'a KdTree<A, T, K, O>
+{
+    type Item = (&'a [A; K], &'a T);
+    type IntoIter = Iter<'a, A, T, K, O>;
 
-        min
+    fn into_iter(self) -> Iter<'a, A, T, K, O> {
+        self.iter()
     }
 }
 
-impl std::error::Error for ErrorKind {}
+impl<'a, A: Float + Zero + One + Signed, T: PartialEq, const K: usize> IntoIterator
+    for &'a mut KdTree<A, T, K>
+{
+    type Item = (&'a [A; K], &'a mut T);
+    type IntoIter = IterMut<'a, A, T, K>;
 
-impl std::fmt::Display for ErrorKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let reason = match *self {
-            ErrorKind::PeriodicOutOfBounds => "out-of-bounds when using periodic boundary conditions",
-            ErrorKind::NonFiniteCoordinate => "non-finite coordinate",
-            ErrorKind::ZeroCapacity => "zero capacity",
-            ErrorKind::Empty => "invalid operation on empty tree",
-        };
-        write!(f, "KdTree error: {}", reason)
+    fn into_iter(self) -> IterMut<'a, A, T, K> {
+        self.iter_mut()
+    }
+}
+
+pub fn get_distance<'a, 'b, A, F, const K: usize>(
+    a: &[A; K],
+    b: &[A; K],
+    distance: &F,
+    periodic: Option<[A; K]>,
+) -> A
+where
+    A: 'a + 'b + Float,
+    F: Fn(&[A; K], &[A; K]) -> A,
+{
+    // If not using periodic boundary conditions, just calculate and return distance
+    match periodic {
+        None => distance(a, b),
+        Some(size) => PeriodicBox::from(size).distance(a, b, distance),
     }
 }
 
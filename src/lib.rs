@@ -1,5 +1,8 @@
 #![doc(html_root_url = "https://docs.rs/kiddo/0.2.4")]
 #![doc(issue_tracker_base_url = "https://github.com/sdd/kiddo/issues/")]
+#![cfg_attr(not(feature = "std"), no_std)]
+// Much of the documentation links to the trees that require `alloc`
+#![cfg_attr(not(feature = "alloc"), allow(rustdoc::broken_intra_doc_links))]
 
 //! # kiddo
 //!
@@ -66,6 +69,20 @@
 //! );
 //! # Ok::<(), kiddo::ErrorKind>(())
 //! ```
+//!
+//! ## `no_std`
+//!
+//! kiddo depends on `std` by default. With default features disabled and the `alloc`
+//! feature enabled it only requires `alloc`, and uses `libm` for floating point
+//! operations. `ConcurrentKdTree` and the `parallel` feature require `std`.
+//!
+//! Where even an allocator is unavailable, disable default features and enable the
+//! `heapless` feature instead. `StaticKdTree` holds a number of elements fixed at
+//! compile time, and never allocates; without the `alloc` feature, kiddo does not link
+//! `alloc` at all.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 #[cfg(feature = "serialize")]
 extern crate serde;
@@ -73,11 +90,41 @@ extern crate serde;
 #[cfg_attr(feature = "serialize", macro_use)]
 extern crate serde_derive;
 
+#[cfg(feature = "std")]
+pub mod concurrent;
 mod custom_serde;
 pub mod distance;
+mod error;
+#[cfg(feature = "alloc")]
 mod heap_element;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "alloc")]
 pub mod kiddo;
+pub mod periodic;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub mod persistent;
+#[cfg(feature = "alloc")]
+pub mod searcher;
+#[cfg(feature = "heapless")]
+pub mod static_tree;
+pub mod stats;
 mod util;
 
-pub use crate::kiddo::ErrorKind;
+#[cfg(feature = "std")]
+pub use crate::concurrent::ConcurrentKdTree;
+pub use crate::error::{ErrorKind, Operation};
+#[cfg(feature = "alloc")]
 pub use crate::kiddo::KdTree;
+pub use crate::periodic::PeriodicBox;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use crate::persistent::PersistentKdTree;
+#[cfg(feature = "alloc")]
+pub use crate::searcher::Searcher;
+#[cfg(feature = "heapless")]
+pub use crate::static_tree::StaticKdTree;
+pub use crate::stats::QueryStats;
+#[cfg(feature = "alloc")]
+pub use crate::stats::TreeStats;
+#[cfg(feature = "heapless")]
+pub use heapless;
@@ -0,0 +1,264 @@
+//! Periodic boundary conditions.
+//!
+//! A [`PeriodicBox`] describes a region of space whose opposite faces are
+//! identified with each other, so that a point leaving through one face re-enters
+//! through the other. Passing one to [`KdTree::new_periodic`] causes the tree to
+//! measure distances between points via the shortest path through the box.
+//!
+//! [`KdTree::new_periodic`]: crate::KdTree::new_periodic
+
+// The distance calculations are only used by the trees that require `alloc`
+#![cfg_attr(not(feature = "alloc"), allow(dead_code))]
+
+use num_traits::Float;
+
+#[cfg(feature = "serialize")]
+use crate::custom_serde::*;
+use crate::error::{ErrorKind, Operation};
+use crate::util;
+
+/// A box with periodic boundary conditions, spanning `lower` to `upper` along
+/// each axis. Only the axes for which `axes` is `true` wrap around; the others
+/// are left open, and points are not constrained to the box along them.
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::{KdTree, PeriodicBox};
+///
+/// // a box of side 10 centred on the origin, whose third axis does not wrap
+/// let periodic = PeriodicBox::new([-5.0, -5.0, -5.0], [5.0, 5.0, 5.0])
+///     .with_axes([true, true, false])
+///     .with_wrapping(true);
+///
+/// let mut tree: KdTree<f64, usize, 3> = KdTree::new_periodic(periodic);
+///
+/// // wrapped back into the box, to [-4.0, 2.0, 50.0]
+/// tree.add(&[6.0, 2.0, 50.0], 100)?;
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serialize",
+    serde(bound(
+        serialize = "A: serde::Serialize",
+        deserialize = "A: serde::Deserialize<'de>"
+    ))
+)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeriodicBox<A, const K: usize> {
+    /// The lower bound of the box along each axis
+    #[cfg_attr(feature = "serialize", serde(with = "arrays"))]
+    pub lower: [A; K],
+    /// The upper bound of the box along each axis
+    #[cfg_attr(feature = "serialize", serde(with = "arrays"))]
+    pub upper: [A; K],
+    /// Whether each axis wraps around
+    #[cfg_attr(feature = "serialize", serde(with = "arrays"))]
+    pub axes: [bool; K],
+    /// Whether points outside the box are wrapped back into it when they are added
+    /// to a tree, rather than being rejected with `ErrorKind::PeriodicOutOfBounds`
+    pub wrap_on_insert: bool,
+}
+
+impl<A: Float, const K: usize> PeriodicBox<A, K> {
+    /// Creates a box spanning `lower` to `upper`, periodic along every axis.
+    pub fn new(lower: [A; K], upper: [A; K]) -> Self {
+        PeriodicBox {
+            lower,
+            upper,
+            axes: [true; K],
+            wrap_on_insert: false,
+        }
+    }
+
+    /// Sets which axes of the box wrap around.
+    pub fn with_axes(mut self, axes: [bool; K]) -> Self {
+        self.axes = axes;
+        self
+    }
+
+    /// Sets whether points outside the box are wrapped back into it when they are
+    /// added to a tree.
+    pub fn with_wrapping(mut self, wrap_on_insert: bool) -> Self {
+        self.wrap_on_insert = wrap_on_insert;
+        self
+    }
+
+    /// Returns the length of the box along each axis
+    pub fn size(&self) -> [A; K] {
+        let mut size = [A::zero(); K];
+        for (idx, length) in size.iter_mut().enumerate() {
+            *length = self.upper[idx] - self.lower[idx];
+        }
+        size
+    }
+
+    /// Returns `true` if `point` lies within the box along every periodic axis
+    pub fn contains(&self, point: &[A; K]) -> bool {
+        (0..K).all(|idx| {
+            !self.axes[idx] || (point[idx] >= self.lower[idx] && point[idx] <= self.upper[idx])
+        })
+    }
+
+    /// Returns the position within the box that is equivalent to `point`. Coordinates
+    /// along axes that do not wrap are left unchanged.
+    pub fn wrap(&self, point: &[A; K]) -> [A; K] {
+        let mut wrapped = *point;
+        for (idx, component) in wrapped.iter_mut().enumerate() {
+            if self.axes[idx] && !(*component >= self.lower[idx] && *component <= self.upper[idx]) {
+                let length = self.upper[idx] - self.lower[idx];
+                let mut offset = (*component - self.lower[idx]) % length;
+                if offset < A::zero() {
+                    offset = offset + length;
+                }
+
+                // guard against rounding up to the upper bound from just below the lower one
+                *component = (self.lower[idx] + offset).min(self.upper[idx]);
+            }
+        }
+        wrapped
+    }
+
+    // Checks that the bounds are finite, and the box has a positive length along each
+    // of its periodic axes
+    pub(crate) fn validate(&self) -> Result<(), ErrorKind> {
+        let invalid_axis = (0..K).find(|&idx| {
+            self.axes[idx]
+                && !(self.lower[idx].is_finite()
+                    && self.upper[idx].is_finite()
+                    && self.lower[idx] < self.upper[idx])
+        });
+
+        match invalid_axis {
+            None => Ok(()),
+            Some(axis) => Err(ErrorKind::InvalidPeriodicBox {
+                axis,
+                lower: util::to_f64(self.lower[axis]),
+                upper: util::to_f64(self.upper[axis]),
+            }),
+        }
+    }
+
+    // Checks that `point` lies within the box along every periodic axis
+    pub(crate) fn check_contains(
+        &self,
+        point: &[A; K],
+        operation: Operation,
+    ) -> Result<(), ErrorKind> {
+        let outside_axis = (0..K).find(|&idx| {
+            self.axes[idx] && !(point[idx] >= self.lower[idx] && point[idx] <= self.upper[idx])
+        });
+
+        match outside_axis {
+            None => Ok(()),
+            Some(axis) => Err(ErrorKind::PeriodicOutOfBounds {
+                operation,
+                axis,
+                value: util::to_f64(point[axis]),
+                lower: util::to_f64(self.lower[axis]),
+                upper: util::to_f64(self.upper[axis]),
+            }),
+        }
+    }
+
+    // Calculates the distance between `a` and the nearest periodic image of `b`, using the
+    // minimum image convention: along each periodic axis, `b` is shifted by a box length
+    // if that brings it within half a box length of `a`
+    pub(crate) fn distance<F>(&self, a: &[A; K], b: &[A; K], distance: &F) -> A
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let mut image = *b;
+        for (idx, component) in image.iter_mut().enumerate() {
+            if self.axes[idx] {
+                let length = self.upper[idx] - self.lower[idx];
+                let half_length = length / (A::one() + A::one());
+                let delta = *component - a[idx];
+
+                if delta > half_length {
+                    *component = *component - length;
+                } else if delta < -half_length {
+                    *component = *component + length;
+                }
+            }
+        }
+
+        distance(a, &image)
+    }
+
+    // Calculates the distance from `point` to the nearest periodic image of the space
+    // bounded by `min_bounds` and `max_bounds`
+    pub(crate) fn distance_to_space<F>(
+        &self,
+        point: &[A; K],
+        min_bounds: &[A; K],
+        max_bounds: &[A; K],
+        distance: &F,
+    ) -> A
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.distance_between_spaces(point, point, min_bounds, max_bounds, distance)
+    }
+
+    // Calculates the distance between the nearest periodic images of two spaces
+    pub(crate) fn distance_between_spaces<F>(
+        &self,
+        min_bounds_1: &[A; K],
+        max_bounds_1: &[A; K],
+        min_bounds_2: &[A; K],
+        max_bounds_2: &[A; K],
+        distance: &F,
+    ) -> A
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let mut p1 = [A::zero(); K];
+        let mut p2 = [A::zero(); K];
+
+        for idx in 0..K {
+            let (mut c1, mut c2) = util::closest_in_intervals(
+                min_bounds_1[idx],
+                max_bounds_1[idx],
+                min_bounds_2[idx],
+                max_bounds_2[idx],
+            );
+
+            if self.axes[idx] {
+                let length = self.upper[idx] - self.lower[idx];
+                for &shift in &[-length, length] {
+                    let (s1, s2) = util::closest_in_intervals(
+                        min_bounds_1[idx] + shift,
+                        max_bounds_1[idx] + shift,
+                        min_bounds_2[idx],
+                        max_bounds_2[idx],
+                    );
+                    if (s2 - s1).abs() < (c2 - c1).abs() {
+                        c1 = s1;
+                        c2 = s2;
+                    }
+                }
+            }
+
+            p1[idx] = c1;
+            p2[idx] = c2;
+        }
+
+        distance(&p1, &p2)
+    }
+}
+
+impl<A: Float, const K: usize> From<[A; K]> for PeriodicBox<A, K> {
+    /// Creates a box spanning from the origin to `size`, periodic along every axis.
+    fn from(size: [A; K]) -> Self {
+        PeriodicBox::new([A::zero(); K], size)
+    }
+}
+
+impl<A: Float, const K: usize> From<&[A; K]> for PeriodicBox<A, K> {
+    /// Creates a box spanning from the origin to `size`, periodic along every axis.
+    fn from(size: &[A; K]) -> Self {
+        PeriodicBox::from(*size)
+    }
+}
@@ -0,0 +1,336 @@
+//! A tree whose snapshots are preserved while it is modified.
+//!
+//! A [`PersistentKdTree`] is built from the same nodes as a [`KdTree`], but each stem
+//! holds its children through an [`Arc`], so that copies of the tree share the nodes
+//! they have in common. Taking a [`snapshot`] only copies a pointer to the root, and
+//! modifying the tree afterwards copies just the nodes on the path from the root to the
+//! leaf being modified, leaving every snapshot unchanged. This makes it cheap to keep
+//! point-in-time views of a tree, for example to run reproducible queries while the live
+//! tree keeps changing.
+//!
+//! [`snapshot`]: PersistentKdTree::snapshot
+
+use alloc::sync::Arc;
+use core::ops::Deref;
+
+use num_traits::{Float, One, Signed, Zero};
+
+use crate::kiddo::{ErrorKind, KdTree, Node, Operation, Shared, DEFAULT_CAPACITY};
+use crate::periodic::PeriodicBox;
+
+/// A kd tree with O(1) snapshots, whose modifications copy only the nodes they change.
+///
+/// A `PersistentKdTree` dereferences to a [`KdTree`] whose children are [`Shared`], so
+/// it supports every query, iterator and statistic that a `KdTree` does, and nodes are
+/// split in the same way. Modifying the tree requires `T: Clone`, as elements stored in
+/// a leaf that is shared with a snapshot are copied along with the leaf.
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::PersistentKdTree;
+/// use kiddo::distance::squared_euclidean;
+///
+/// let mut tree: PersistentKdTree<f64, usize, 3> = PersistentKdTree::new();
+///
+/// tree.add(&[1.0, 2.0, 5.0], 100)?;
+/// let snapshot = tree.snapshot();
+///
+/// tree.add(&[1.0, 2.0, 5.1], 101)?;
+/// tree.remove(&[1.0, 2.0, 5.0], &100)?;
+///
+/// assert_eq!(*tree.nearest_one(&[1.0, 2.0, 5.0], &squared_euclidean)?.1, 101);
+/// assert_eq!(*snapshot.nearest_one(&[1.0, 2.0, 5.0], &squared_euclidean)?.1, 100);
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+pub struct PersistentKdTree<A, T: PartialEq, const K: usize> {
+    root: Arc<KdTree<A, T, K, Shared>>,
+}
+
+impl<A, T: PartialEq, const K: usize> Clone for PersistentKdTree<A, T, K> {
+    fn clone(&self) -> Self {
+        PersistentKdTree {
+            root: Arc::clone(&self.root),
+        }
+    }
+}
+
+impl<A, T: PartialEq, const K: usize> Deref for PersistentKdTree<A, T, K> {
+    type Target = KdTree<A, T, K, Shared>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.root
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize> Default
+    for PersistentKdTree<A, T, K>
+{
+    fn default() -> Self {
+        PersistentKdTree::new()
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize> From<KdTree<A, T, K>>
+    for PersistentKdTree<A, T, K>
+{
+    /// Moves the elements of `tree` into a new `PersistentKdTree` with the same capacity
+    /// per node and periodic boundary conditions, rebuilding it in one pass.
+    fn from(tree: KdTree<A, T, K>) -> Self {
+        let capacity = tree.capacity();
+        let periodic = tree.periodic().copied();
+        let (points, bucket) = tree.into_iter().unzip();
+
+        PersistentKdTree {
+            root: Arc::new(KdTree::build(capacity, periodic, points, bucket)),
+        }
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq, const K: usize> PersistentKdTree<A, T, K> {
+    /// Creates a new PersistentKdTree with default capacity **per node** of 16.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::PersistentKdTree;
+    ///
+    /// let mut tree: PersistentKdTree<f64, usize, 3> = PersistentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn new() -> Self {
+        PersistentKdTree::with_per_node_capacity(DEFAULT_CAPACITY).unwrap()
+    }
+
+    /// Creates a new PersistentKdTree with a specific capacity **per node**.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::PersistentKdTree;
+    ///
+    /// let mut tree: PersistentKdTree<f64, usize, 3> =
+    ///     PersistentKdTree::with_per_node_capacity(30)?;
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn with_per_node_capacity(capacity: usize) -> Result<Self, ErrorKind> {
+        Ok(PersistentKdTree {
+            root: Arc::new(KdTree::empty(capacity, None)?),
+        })
+    }
+
+    /// Creates a new PersistentKdTree with a specific capacity **per node**, and with
+    /// periodic boundary conditions, as described for
+    /// [`KdTree::periodic_with_per_node_capacity`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::PersistentKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: PersistentKdTree<f64, usize, 3> =
+    ///     PersistentKdTree::periodic_with_per_node_capacity(30, [10.0, 10.0, 10.0])?;
+    ///
+    /// tree.add(&[1.0, 2.0, 0.5], 100)?;
+    /// tree.add(&[1.0, 2.0, 8.0], 101)?;
+    ///
+    /// assert_eq!(*tree.nearest_one(&[1.0, 2.0, 9.9], &squared_euclidean)?.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn periodic_with_per_node_capacity<P: Into<PeriodicBox<A, K>>>(
+        capacity: usize,
+        periodic: P,
+    ) -> Result<Self, ErrorKind> {
+        Ok(PersistentKdTree {
+            root: Arc::new(KdTree::empty(capacity, Some(periodic.into()))?),
+        })
+    }
+
+    /// Returns a snapshot of the tree in its current state, which later modifications
+    /// of this tree do not affect. Taking a snapshot takes constant time, as it shares
+    /// all of its nodes with this tree.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::PersistentKdTree;
+    ///
+    /// let mut tree: PersistentKdTree<f64, usize, 3> = PersistentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// let snapshot = tree.snapshot();
+    /// tree.add(&[1.1, 2.1, 5.1], 101)?;
+    ///
+    /// assert_eq!(snapshot.size(), 1);
+    /// assert_eq!(tree.size(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+}
+
+impl<A: Float + Zero + One + Signed, T: PartialEq + Clone, const K: usize>
+    PersistentKdTree<A, T, K>
+{
+    /// Add an element to the tree, as described for [`KdTree::add`]. Only the nodes on
+    /// the path to the leaf that the element is added to are copied, and only if they
+    /// are shared with a snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::PersistentKdTree;
+    ///
+    /// let mut tree: PersistentKdTree<f64, usize, 3> = PersistentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[1.1, 2.1, 5.1], 101)?;
+    ///
+    /// assert_eq!(tree.size(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn add(&mut self, point: &[A; K], data: T) -> Result<(), ErrorKind> {
+        let point = self.root.wrap_point(point);
+        self.root.check_point(&point, Operation::Add)?;
+
+        add_to(Arc::make_mut(&mut self.root), &point, data);
+
+        Ok(())
+    }
+
+    /// Removes every element stored at `point` that is equal to `data`, returning the
+    /// number of elements removed. As with `add()`, only the nodes on the path to the
+    /// affected leaf are copied.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::PersistentKdTree;
+    ///
+    /// let mut tree: PersistentKdTree<f64, usize, 3> = PersistentKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[1.1, 2.1, 5.1], 101)?;
+    ///
+    /// assert_eq!(tree.remove(&[1.0, 2.0, 5.0], &100)?, 1);
+    /// assert_eq!(tree.size(), 1);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn remove(&mut self, point: &[A; K], data: &T) -> Result<usize, ErrorKind> {
+        let point = self.root.wrap_point(point);
+        self.root.check_point(&point, Operation::Remove)?;
+
+        // Avoid copying any nodes if there is nothing to remove
+        if !holds(&self.root, &point, data) {
+            return Ok(0);
+        }
+
+        Ok(remove_from(Arc::make_mut(&mut self.root), &point, data))
+    }
+}
+
+// Adds an element beneath `node` in the same way as `KdTree::add`, first copying each
+// child on the way down that is shared with another tree
+fn add_to<A, T, const K: usize>(node: &mut KdTree<A, T, K, Shared>, point: &[A; K], data: T)
+where
+    A: Float + Zero + One + Signed,
+    T: PartialEq + Clone,
+{
+    match &mut node.content {
+        Node::Leaf { .. } => return node.add_to_bucket(point, data),
+        Node::Stem {
+            left,
+            right,
+            split_value,
+            split_dimension,
+        } => {
+            let child = if point[*split_dimension as usize] < *split_value {
+                left
+            } else {
+                right
+            };
+            add_to(Arc::make_mut(child), point, data);
+        }
+    }
+
+    node.extend_bounds(point);
+    node.size += 1;
+}
+
+// Removes every element equal to `data` at `point` beneath `node`, copying shared
+// children on the way down as `add_to` does. Elements are always stored on the side of
+// a stem's split that their coordinates belong on, so only one path needs to be visited.
+fn remove_from<A, T, const K: usize>(
+    node: &mut KdTree<A, T, K, Shared>,
+    point: &[A; K],
+    data: &T,
+) -> usize
+where
+    A: Float + Zero + One + Signed,
+    T: PartialEq + Clone,
+{
+    let removed = match &mut node.content {
+        Node::Stem {
+            left,
+            right,
+            split_value,
+            split_dimension,
+        } => {
+            let child = if point[*split_dimension as usize] < *split_value {
+                left
+            } else {
+                right
+            };
+            remove_from(Arc::make_mut(child), point, data)
+        }
+        Node::Leaf { points, bucket, .. } => {
+            let mut removed = 0;
+            let mut idx = 0;
+            while idx < points.len() {
+                if &points[idx] == point && &bucket[idx] == data {
+                    points.swap_remove(idx);
+                    bucket.swap_remove(idx);
+                    removed += 1;
+                } else {
+                    idx += 1;
+                }
+            }
+            removed
+        }
+    };
+
+    node.size -= removed;
+    removed
+}
+
+// Returns true if an element equal to `data` is stored at `point` beneath `node`
+fn holds<A, T, const K: usize>(node: &KdTree<A, T, K, Shared>, point: &[A; K], data: &T) -> bool
+where
+    A: Float,
+    T: PartialEq,
+{
+    match &node.content {
+        Node::Stem {
+            left,
+            right,
+            split_value,
+            split_dimension,
+        } => {
+            if point[*split_dimension as usize] < *split_value {
+                holds(left, point, data)
+            } else {
+                holds(right, point, data)
+            }
+        }
+        Node::Leaf { points, bucket, .. } => points
+            .iter()
+            .zip(bucket.iter())
+            .any(|(p, d)| p == point && d == data),
+    }
+}
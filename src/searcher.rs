@@ -0,0 +1,296 @@
+//! A query context that reuses its working memory across queries.
+//!
+//! Every call to [`KdTree::nearest`] or [`KdTree::within`] allocates fresh heaps
+//! to track pending nodes and evaluated elements. When performing many queries
+//! in a tight loop against the same tree, a [`Searcher`] holds on to those
+//! buffers (and to the buffer the results are written into), so that after the
+//! first few queries no further allocation takes place.
+
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+
+use num_traits::{Float, One, Signed, Zero};
+
+use crate::heap_element::HeapElement;
+use crate::kiddo::{Candidate, ErrorKind, KdTree};
+
+/// Performs repeated queries against a [`KdTree`] without allocating per query.
+///
+/// Created by [`KdTree::searcher`]. Results are returned as slices that borrow
+/// from the `Searcher`, and remain valid until the next query.
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::KdTree;
+/// use kiddo::distance::squared_euclidean;
+///
+/// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+///
+/// tree.add(&[1.0, 2.0, 5.0], 100)?;
+/// tree.add(&[2.0, 3.0, 6.0], 101)?;
+///
+/// let mut searcher = tree.searcher();
+///
+/// for query in &[[1.0, 2.0, 5.1], [2.0, 3.0, 6.1]] {
+///     let nearest = searcher.nearest(query, 1, &squared_euclidean)?;
+///     assert_eq!(nearest.len(), 1);
+/// }
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+pub struct Searcher<'t, A, T: PartialEq, const K: usize> {
+    tree: &'t KdTree<A, T, K>,
+    pending: BinaryHeap<HeapElement<A, &'t KdTree<A, T, K>>>,
+    pending_stack: Vec<HeapElement<A, &'t KdTree<A, T, K>>>,
+    evaluated: BinaryHeap<Candidate<'t, A, T, K>>,
+    results: Vec<(A, &'t T)>,
+}
+
+impl<'t, A: Float + Zero + One + Signed, T: PartialEq, const K: usize> Searcher<'t, A, T, K> {
+    pub(crate) fn new(tree: &'t KdTree<A, T, K>) -> Self {
+        Searcher {
+            tree,
+            pending: BinaryHeap::new(),
+            pending_stack: Vec::with_capacity(16),
+            evaluated: BinaryHeap::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Returns the tree that this searcher queries
+    pub fn tree(&self) -> &'t KdTree<A, T, K> {
+        self.tree
+    }
+
+    /// Queries the tree to find the nearest `num` elements to `point`, using the specified
+    /// distance metric function. Equivalent to [`KdTree::nearest`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let mut searcher = tree.searcher();
+    /// let nearest = searcher.nearest(&[1.0, 2.0, 5.1], 1, &squared_euclidean)?;
+    ///
+    /// assert_eq!(nearest.len(), 1);
+    /// assert!((nearest[0].0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest[0].1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest<F>(
+        &mut self,
+        point: &[A; K],
+        num: usize,
+        distance: &F,
+    ) -> Result<&[(A, &'t T)], ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.results.clear();
+        self.tree.nearest_search(
+            point,
+            num,
+            A::infinity(),
+            &self.tree.metric(distance),
+            &|_| true,
+            &mut self.pending,
+            &mut self.evaluated,
+        )?;
+
+        Ok(self.drain_sorted())
+    }
+
+    /// Queries the tree to find the nearest `num` elements to `point` that are no further
+    /// than `max_radius` from it, using the specified distance metric function. Equivalent
+    /// to [`KdTree::nearest_within`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let mut searcher = tree.searcher();
+    /// let nearest = searcher.nearest_within(&[1.0, 2.0, 5.1], 3, 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(nearest.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_within<F>(
+        &mut self,
+        point: &[A; K],
+        num: usize,
+        max_radius: A,
+        distance: &F,
+    ) -> Result<&[(A, &'t T)], ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.results.clear();
+        self.tree.nearest_search(
+            point,
+            num,
+            max_radius,
+            &self.tree.metric(distance),
+            &|_| true,
+            &mut self.pending,
+            &mut self.evaluated,
+        )?;
+
+        Ok(self.drain_sorted())
+    }
+
+    /// Queries the tree to find the nearest element to `point`, using the specified
+    /// distance metric function. Equivalent to [`KdTree::nearest_one`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let mut searcher = tree.searcher();
+    /// let nearest = searcher.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// assert!((nearest.0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_one<F>(&mut self, point: &[A; K], distance: &F) -> Result<(A, &'t T), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.tree.nearest_one_search(
+            point,
+            &self.tree.metric(distance),
+            &|_| true,
+            &mut self.pending_stack,
+        )
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned sorted nearest-first. Equivalent to
+    /// [`KdTree::within`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let mut searcher = tree.searcher();
+    /// let within = searcher.within(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within<F>(
+        &mut self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<&[(A, &'t T)], ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.results.clear();
+        if self.tree.size() == 0 {
+            return Ok(&self.results);
+        }
+
+        self.tree.within_search(
+            point,
+            radius,
+            &self.tree.metric(distance),
+            &mut self.pending,
+            &mut self.evaluated,
+        )?;
+
+        Ok(self.drain_sorted())
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the specified
+    /// distance metric function. Results are returned in arbitrary order. Equivalent to
+    /// [`KdTree::within_unsorted`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::KdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let mut searcher = tree.searcher();
+    /// let within = searcher.within_unsorted(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within_unsorted<F>(
+        &mut self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<&[(A, &'t T)], ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        self.results.clear();
+        if self.tree.size() == 0 {
+            return Ok(&self.results);
+        }
+
+        self.tree.within_search(
+            point,
+            radius,
+            &self.tree.metric(distance),
+            &mut self.pending,
+            &mut self.evaluated,
+        )?;
+
+        self.results
+            .extend(self.evaluated.drain().map(|x| (x.distance, x.element.1)));
+
+        Ok(&self.results)
+    }
+
+    // Moves the contents of `evaluated` into `results`, nearest-first, without
+    // giving up the capacity of either buffer.
+    fn drain_sorted(&mut self) -> &[(A, &'t T)] {
+        while let Some(x) = self.evaluated.pop() {
+            self.results.push((x.distance, x.element.1));
+        }
+        self.results.reverse();
+
+        &self.results
+    }
+}
@@ -0,0 +1,705 @@
+//! A fixed-capacity tree that never allocates.
+//!
+//! A [`StaticKdTree`] holds at most `N` elements, and stores its points, elements and
+//! nodes in arrays whose sizes are fixed at compile time, so that neither adding to it
+//! nor querying it allocates. This makes it suitable for real-time code, and for
+//! embedded targets without a heap. Query results are written into a caller-provided
+//! buffer, or returned in a fixed-capacity [`heapless::Vec`].
+//!
+//! Requires the `heapless` feature. It does not require `alloc`, so with default features
+//! disabled and only `heapless` enabled, kiddo can be used without a global allocator.
+
+use heapless::Vec;
+use num_traits::{Float, One, Signed, Zero};
+
+use crate::error::{ErrorKind, Operation};
+use crate::util;
+
+/// A kd tree of at most `N` elements, with at most `B` elements in each leaf, that
+/// never allocates.
+///
+/// Leaves are split halfway along their widest dimension, as in a
+/// [`KdTree`](crate::KdTree). As every leaf holds at least one element once the tree
+/// is non-empty, `N` elements never need more than `N` leaves. Adding an element
+/// returns `ErrorKind::CapacityExceeded` if the tree already holds `N` elements, or if
+/// the element would be the `B + 1`th in a leaf whose elements all share the same
+/// point, which cannot be split.
+///
+/// The tree holds all of its storage inline, and its size depends on `N` but not on `B`:
+/// for each of up to `N` elements, room for its point and item, and for a leaf and a stem.
+/// On 64-bit targets that comes to about
+/// `N * ((5 * K + 1) * size_of::<A>() + size_of::<T>() + 96)` bytes, or 2.3 MB for ten
+/// thousand `f64` points in three dimensions with `usize` items. A tree that size is too
+/// large for many stacks, so should be created in a `static`, which
+/// [`new`](StaticKdTree::new) allows, rather than on the stack. Queries do not recurse,
+/// but keep the nodes they have still to search on a stack of up to `N` of them, of about
+/// `N * (16 + size_of::<A>())` bytes.
+///
+/// Periodic boundary conditions are not supported.
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::StaticKdTree;
+/// use kiddo::distance::squared_euclidean;
+///
+/// let mut tree: StaticKdTree<f64, usize, 3, 64, 8> = StaticKdTree::new();
+///
+/// tree.add(&[1.0, 2.0, 5.0], 100)?;
+/// tree.add(&[2.0, 3.0, 6.0], 101)?;
+///
+/// let nearest = tree.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+///
+/// assert_eq!(*nearest.1, 100);
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+pub struct StaticKdTree<A, T, const K: usize, const N: usize, const B: usize> {
+    points: Vec<[A; K], N>,
+    bucket: Vec<T, N>,
+    // The element after each element in the same leaf, if there is one
+    next: Vec<Option<usize>, N>,
+    stems: Vec<Stem<A, K>, N>,
+    leaves: Vec<Leaf<A, K>, N>,
+    root: NodeId,
+}
+
+#[derive(Clone, Copy)]
+enum NodeId {
+    Stem(usize),
+    Leaf(usize),
+}
+
+struct Stem<A, const K: usize> {
+    min_bounds: [A; K],
+    max_bounds: [A; K],
+    left: NodeId,
+    right: NodeId,
+    split_value: A,
+    split_dimension: usize,
+}
+
+struct Leaf<A, const K: usize> {
+    min_bounds: [A; K],
+    max_bounds: [A; K],
+    // The indices in `points` and `bucket` of the first and last of the leaf's elements,
+    // which are linked together in the order they were added through `next`
+    first: Option<usize>,
+    last: Option<usize>,
+    len: usize,
+}
+
+impl<A, T, const K: usize, const N: usize, const B: usize> Default for StaticKdTree<A, T, K, N, B>
+where
+    A: Float + Zero + One + Signed,
+{
+    fn default() -> Self {
+        StaticKdTree::new()
+    }
+}
+
+impl<A, T, const K: usize, const N: usize, const B: usize> StaticKdTree<A, T, K, N, B>
+where
+    A: Float + Zero + One + Signed,
+{
+    /// Creates a new, empty StaticKdTree. As this is a `const fn`, the tree can be
+    /// created in a `static`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::StaticKdTree;
+    ///
+    /// let mut tree: StaticKdTree<f64, usize, 3, 64, 8> = StaticKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub const fn new() -> Self {
+        StaticKdTree {
+            points: Vec::new(),
+            bucket: Vec::new(),
+            next: Vec::new(),
+            stems: Vec::new(),
+            leaves: Vec::new(),
+            root: NodeId::Leaf(0),
+        }
+    }
+
+    /// Returns the current number of elements stored in the tree
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::StaticKdTree;
+    ///
+    /// let mut tree: StaticKdTree<f64, usize, 3, 64, 8> = StaticKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[1.1, 2.1, 5.1], 101)?;
+    ///
+    /// assert_eq!(tree.size(), 2);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn size(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns the maximum number of elements that the tree can hold, `N`
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Adds an item to the tree.
+    ///
+    /// Returns `ErrorKind::CapacityExceeded`, leaving the tree unchanged, if the tree is
+    /// full or the item cannot be placed in a leaf.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::{ErrorKind, StaticKdTree};
+    ///
+    /// let mut tree: StaticKdTree<f64, usize, 3, 2, 2> = StaticKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[1.1, 2.1, 5.1], 101)?;
+    ///
+    /// assert_eq!(tree.add(&[1.2, 2.2, 5.2], 102), Err(ErrorKind::CapacityExceeded));
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn add(&mut self, point: &[A; K], data: T) -> Result<(), ErrorKind> {
+        util::check_point(point, None, Operation::Add)?;
+
+        if self.points.is_full() {
+            return Err(ErrorKind::CapacityExceeded);
+        }
+
+        if self.leaves.is_empty() {
+            self.leaves
+                .push(Leaf {
+                    min_bounds: *point,
+                    max_bounds: *point,
+                    first: None,
+                    last: None,
+                    len: 0,
+                })
+                .map_err(|_| ErrorKind::CapacityExceeded)?;
+        }
+
+        let (leaf, parent) = self.find_leaf(point);
+
+        let split = if self.leaves[leaf].len >= B {
+            let plane = self
+                .split_plane(leaf, point)
+                .ok_or(ErrorKind::CapacityExceeded)?;
+            if self.stems.is_full() || self.leaves.is_full() {
+                return Err(ErrorKind::CapacityExceeded);
+            }
+            Some(plane)
+        } else {
+            None
+        };
+
+        let index = self.points.len();
+        self.points
+            .push(*point)
+            .map_err(|_| ErrorKind::CapacityExceeded)?;
+        self.bucket
+            .push(data)
+            .map_err(|_| ErrorKind::CapacityExceeded)?;
+        self.next
+            .push(None)
+            .map_err(|_| ErrorKind::CapacityExceeded)?;
+        self.extend_path(point);
+
+        match split {
+            None => self.leaves[leaf].push(index, point, &mut self.next),
+            Some((split_dimension, split_value)) => {
+                self.split(leaf, parent, index, split_dimension, split_value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Queries the tree to find the nearest element to `point`, using the specified
+    /// distance metric function. Elements whose distance is `NaN` are only returned if
+    /// no element has a distance that can be compared.
+    ///
+    /// Returns `ErrorKind::Empty` if the tree is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::StaticKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: StaticKdTree<f64, usize, 3, 64, 8> = StaticKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    ///
+    /// let nearest = tree.nearest_one(&[1.0, 2.0, 5.1], &squared_euclidean)?;
+    ///
+    /// assert!((nearest.0 - 0.01f64).abs() < f64::EPSILON);
+    /// assert_eq!(*nearest.1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest_one<F>(&self, point: &[A; K], distance: &F) -> Result<(A, &T), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        util::check_point(point, None, Operation::Query)?;
+
+        if self.points.is_empty() {
+            return Err(ErrorKind::Empty);
+        }
+
+        self.nearest_one_search(point, distance)
+            .map(|(d, index)| (d, &self.bucket[index]))
+            .ok_or(ErrorKind::Empty)
+    }
+
+    /// Queries the tree to find the nearest elements to `point`, using the specified
+    /// distance metric function, filling `results` with as many of them as it has room
+    /// for. Results are written nearest-first, and the number of results written is
+    /// returned. Any slots in `results` beyond that number are set to `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::StaticKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    ///
+    /// let mut tree: StaticKdTree<f64, usize, 3, 64, 8> = StaticKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let mut results = [None; 2];
+    /// let found = tree.nearest(&[1.0, 2.0, 5.1], &mut results, &squared_euclidean)?;
+    ///
+    /// assert_eq!(found, 2);
+    /// assert_eq!(*results[0].unwrap().1, 100);
+    /// assert_eq!(*results[1].unwrap().1, 101);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn nearest<'a, F>(
+        &'a self,
+        point: &[A; K],
+        results: &mut [Option<(A, &'a T)>],
+        distance: &F,
+    ) -> Result<usize, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        util::check_point(point, None, Operation::Query)?;
+
+        results.iter_mut().for_each(|result| *result = None);
+        if self.points.is_empty() || results.is_empty() {
+            return Ok(0);
+        }
+
+        Ok(self.nearest_search(point, distance, results))
+    }
+
+    /// Queries the tree to find all elements within `radius` of `point`, using the
+    /// specified distance metric function. Results are returned sorted nearest-first, in
+    /// a vector that can hold up to `M` of them.
+    ///
+    /// Returns `ErrorKind::CapacityExceeded` if more than `M` elements lie within
+    /// `radius`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kiddo::StaticKdTree;
+    /// use kiddo::distance::squared_euclidean;
+    /// use kiddo::heapless::Vec;
+    ///
+    /// let mut tree: StaticKdTree<f64, usize, 3, 64, 8> = StaticKdTree::new();
+    ///
+    /// tree.add(&[1.0, 2.0, 5.0], 100)?;
+    /// tree.add(&[2.0, 3.0, 6.0], 101)?;
+    /// tree.add(&[200.0, 300.0, 600.0], 102)?;
+    ///
+    /// let within: Vec<_, 4> = tree.within(&[1.0, 2.0, 5.0], 10f64, &squared_euclidean)?;
+    ///
+    /// assert_eq!(within.len(), 2);
+    /// assert_eq!(*within[0].1, 100);
+    /// # Ok::<(), kiddo::ErrorKind>(())
+    /// ```
+    pub fn within<F, const M: usize>(
+        &self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+    ) -> Result<Vec<(A, &T), M>, ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        util::check_point(point, None, Operation::Query)?;
+
+        let mut results = Vec::new();
+        if self.points.is_empty() {
+            return Ok(results);
+        }
+
+        self.within_search(point, radius, distance, &mut results)?;
+        results
+            .sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+
+        Ok(results)
+    }
+
+    // Returns the indices of a leaf's elements in `points` and `bucket`
+    fn elements(&self, leaf: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut next = self.leaves[leaf].first;
+        core::iter::from_fn(move || {
+            let index = next?;
+            next = self.next[index];
+            Some(index)
+        })
+    }
+
+    fn bounds(&self, node: NodeId) -> (&[A; K], &[A; K]) {
+        match node {
+            NodeId::Stem(stem) => (&self.stems[stem].min_bounds, &self.stems[stem].max_bounds),
+            NodeId::Leaf(leaf) => (&self.leaves[leaf].min_bounds, &self.leaves[leaf].max_bounds),
+        }
+    }
+
+    // Returns the leaf in which `point` belongs, and the stem above it along with
+    // whether the leaf is that stem's left child
+    fn find_leaf(&self, point: &[A; K]) -> (usize, Option<(usize, bool)>) {
+        let mut node = self.root;
+        let mut parent = None;
+        loop {
+            match node {
+                NodeId::Stem(stem) => {
+                    let stem_node = &self.stems[stem];
+                    let left = point[stem_node.split_dimension] < stem_node.split_value;
+                    parent = Some((stem, left));
+                    node = if left {
+                        stem_node.left
+                    } else {
+                        stem_node.right
+                    };
+                }
+                NodeId::Leaf(leaf) => return (leaf, parent),
+            }
+        }
+    }
+
+    // Extends the bounds of every stem on the path to the leaf in which `point` belongs
+    fn extend_path(&mut self, point: &[A; K]) {
+        let mut node = self.root;
+        while let NodeId::Stem(stem) = node {
+            let stem_node = &mut self.stems[stem];
+            extend_bounds(&mut stem_node.min_bounds, &mut stem_node.max_bounds, point);
+            node = if point[stem_node.split_dimension] < stem_node.split_value {
+                stem_node.left
+            } else {
+                stem_node.right
+            };
+        }
+    }
+
+    // Chooses where to split a full leaf once `point` is added to it, or returns `None`
+    // if there is no plane that would leave elements on both sides
+    fn split_plane(&self, leaf_index: usize, point: &[A; K]) -> Option<(usize, A)> {
+        let leaf = &self.leaves[leaf_index];
+        let mut min_bounds = leaf.min_bounds;
+        let mut max_bounds = leaf.max_bounds;
+        extend_bounds(&mut min_bounds, &mut max_bounds, point);
+
+        let (split_dimension, split_value) = util::split_plane(&min_bounds, &max_bounds)?;
+        let lefts = self
+            .elements(leaf_index)
+            .map(|index| &self.points[index])
+            .chain(core::iter::once(point))
+            .filter(|p| p[split_dimension] < split_value)
+            .count();
+
+        if lefts == 0 || lefts == leaf.len + 1 {
+            None
+        } else {
+            Some((split_dimension, split_value))
+        }
+    }
+
+    // Splits a full leaf into two, adding the element at `index` to whichever of them
+    // it belongs in
+    fn split(
+        &mut self,
+        leaf: usize,
+        parent: Option<(usize, bool)>,
+        index: usize,
+        split_dimension: usize,
+        split_value: A,
+    ) -> Result<(), ErrorKind> {
+        let mut left = Leaf::empty();
+        let mut right = Leaf::empty();
+
+        // The new element at `index` follows the last of the full leaf's elements
+        let mut next = self.leaves[leaf].first;
+        while let Some(element) = next {
+            next = match self.next[element] {
+                None if element != index => Some(index),
+                next => next,
+            };
+            let point = &self.points[element];
+            let side = if point[split_dimension] < split_value {
+                &mut left
+            } else {
+                &mut right
+            };
+            side.push(element, point, &mut self.next);
+        }
+
+        let mut stem = Stem {
+            min_bounds: self.leaves[leaf].min_bounds,
+            max_bounds: self.leaves[leaf].max_bounds,
+            left: NodeId::Leaf(leaf),
+            right: NodeId::Leaf(self.leaves.len()),
+            split_value,
+            split_dimension,
+        };
+        extend_bounds(
+            &mut stem.min_bounds,
+            &mut stem.max_bounds,
+            &self.points[index],
+        );
+
+        self.leaves[leaf] = left;
+        self.leaves
+            .push(right)
+            .map_err(|_| ErrorKind::CapacityExceeded)?;
+
+        let id = NodeId::Stem(self.stems.len());
+        self.stems
+            .push(stem)
+            .map_err(|_| ErrorKind::CapacityExceeded)?;
+
+        match parent {
+            None => self.root = id,
+            Some((parent, true)) => self.stems[parent].left = id,
+            Some((parent, false)) => self.stems[parent].right = id,
+        }
+
+        Ok(())
+    }
+
+    fn nearest_one_search<F>(&self, point: &[A; K], distance: &F) -> Option<(A, usize)>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let mut best: Option<(A, usize)> = None;
+        let mut stack = Stack::new(self.root);
+        while let Some((node, d)) = stack.pop() {
+            let reachable = match best {
+                Some((best, _)) => d <= best || best.is_nan(),
+                None => true,
+            };
+            if !reachable {
+                continue;
+            }
+
+            match node {
+                NodeId::Leaf(leaf) => {
+                    for index in self.elements(leaf) {
+                        let d = distance(point, &self.points[index]);
+                        let closer = match best {
+                            Some((best, _)) => d < best || best.is_nan(),
+                            None => true,
+                        };
+                        if closer {
+                            best = Some((d, index));
+                        }
+                    }
+                }
+                NodeId::Stem(stem) => self.push_children(&mut stack, stem, point, distance),
+            }
+        }
+
+        best
+    }
+
+    fn nearest_search<'a, F>(
+        &'a self,
+        point: &[A; K],
+        distance: &F,
+        results: &mut [Option<(A, &'a T)>],
+    ) -> usize
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let mut found = 0;
+        let mut stack = Stack::new(self.root);
+        while let Some((node, d)) = stack.pop() {
+            if found == results.len() && d > furthest(results, found) {
+                continue;
+            }
+
+            match node {
+                NodeId::Leaf(leaf) => {
+                    for index in self.elements(leaf) {
+                        let d = distance(point, &self.points[index]);
+                        insert_sorted(results, &mut found, (d, &self.bucket[index]));
+                    }
+                }
+                NodeId::Stem(stem) => self.push_children(&mut stack, stem, point, distance),
+            }
+        }
+
+        found
+    }
+
+    fn within_search<'a, F, const M: usize>(
+        &'a self,
+        point: &[A; K],
+        radius: A,
+        distance: &F,
+        results: &mut Vec<(A, &'a T), M>,
+    ) -> Result<(), ErrorKind>
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let mut stack = Stack::new(self.root);
+        while let Some((node, d)) = stack.pop() {
+            if d > radius {
+                continue;
+            }
+
+            match node {
+                NodeId::Leaf(leaf) => {
+                    for index in self.elements(leaf) {
+                        let d = distance(point, &self.points[index]);
+                        if d <= radius {
+                            results
+                                .push((d, &self.bucket[index]))
+                                .map_err(|_| ErrorKind::CapacityExceeded)?;
+                        }
+                    }
+                }
+                NodeId::Stem(stem) => self.push_children(&mut stack, stem, point, distance),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Pushes a stem's children onto the stack of nodes still to be searched, along with
+    // their distances from `point`, so that the nearest of them is searched first
+    fn push_children<F>(&self, stack: &mut Stack<A, N>, stem: usize, point: &[A; K], distance: &F)
+    where
+        F: Fn(&[A; K], &[A; K]) -> A,
+    {
+        let stem = &self.stems[stem];
+        let to_child = |child: NodeId| {
+            let (min_bounds, max_bounds) = self.bounds(child);
+            (
+                child,
+                util::distance_to_space(point, min_bounds, max_bounds, distance),
+            )
+        };
+
+        let left = to_child(stem.left);
+        let right = to_child(stem.right);
+        if right.1 < left.1 {
+            stack.push(left);
+            stack.push(right);
+        } else {
+            stack.push(right);
+            stack.push(left);
+        }
+    }
+}
+
+// The nodes still to be searched by a query, with their distances from the query's point,
+// which take the place of recursion so that a query's use of the call stack is bounded
+// however unbalanced the tree is. A stem popped from the stack is replaced by its two
+// children, so the stack holds at most one node more than the number of stems on a path
+// from the root, and as the tree has fewer stems than `N`, never more than `N` nodes.
+struct Stack<A, const N: usize> {
+    nodes: Vec<(NodeId, A), N>,
+}
+
+impl<A: Float, const N: usize> Stack<A, N> {
+    fn new(root: NodeId) -> Self {
+        let mut stack = Stack { nodes: Vec::new() };
+        stack.push((root, A::neg_infinity()));
+        stack
+    }
+
+    fn push(&mut self, node: (NodeId, A)) {
+        let pushed = self.nodes.push(node);
+        debug_assert!(pushed.is_ok(), "search stack overflowed");
+    }
+
+    fn pop(&mut self) -> Option<(NodeId, A)> {
+        self.nodes.pop()
+    }
+}
+
+impl<A: Float, const K: usize> Leaf<A, K> {
+    fn empty() -> Self {
+        Leaf {
+            min_bounds: [A::infinity(); K],
+            max_bounds: [A::neg_infinity(); K],
+            first: None,
+            last: None,
+            len: 0,
+        }
+    }
+
+    // Adds the element at `index`, at `point`, after the leaf's other elements
+    fn push(&mut self, index: usize, point: &[A; K], next: &mut [Option<usize>]) {
+        extend_bounds(&mut self.min_bounds, &mut self.max_bounds, point);
+        match self.last {
+            Some(last) => next[last] = Some(index),
+            None => self.first = Some(index),
+        }
+        next[index] = None;
+        self.last = Some(index);
+        self.len += 1;
+    }
+}
+
+fn extend_bounds<A: Float, const K: usize>(
+    min_bounds: &mut [A; K],
+    max_bounds: &mut [A; K],
+    point: &[A; K],
+) {
+    for ((min, max), &value) in min_bounds.iter_mut().zip(max_bounds.iter_mut()).zip(point) {
+        *min = min.min(value);
+        *max = max.max(value);
+    }
+}
+
+// Returns the distance to the furthest of the `found` results
+fn furthest<A: Float, T>(results: &[Option<(A, T)>], found: usize) -> A {
+    results[found - 1]
+        .as_ref()
+        .map_or(A::infinity(), |result| result.0)
+}
+
+// Inserts `result` into `results`, which holds `found` results sorted nearest-first,
+// dropping the furthest result if there is no room for it
+fn insert_sorted<A: Float, T>(results: &mut [Option<(A, T)>], found: &mut usize, result: (A, T)) {
+    if *found == results.len() && result.0 >= furthest(results, *found) {
+        return;
+    }
+
+    let position = results[..*found]
+        .iter()
+        .position(|r| matches!(r, Some(r) if result.0 < r.0))
+        .unwrap_or(*found);
+
+    if *found < results.len() {
+        *found += 1;
+    }
+    results[position..*found].rotate_right(1);
+    results[position] = Some(result);
+}
@@ -0,0 +1,175 @@
+//! Statistics describing the shape of a tree, and the work done by queries.
+//!
+//! [`TreeStats`], as returned by [`KdTree::stats`], summarises how a tree's elements
+//! are spread across its nodes, which helps when choosing a capacity per node or
+//! diagnosing slow queries. [`QueryStats`], as returned by the `*_with_stats` query
+//! methods such as [`KdTree::nearest_with_stats`], counts the nodes a single query
+//! visited and the distances it calculated.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use num_traits::{Float, One, Signed, Zero};
+
+#[cfg(feature = "alloc")]
+use crate::kiddo::{KdTree, Node, Ownership};
+
+/// Summary statistics for a [`KdTree`], as returned by [`KdTree::stats`].
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::KdTree;
+///
+/// let mut tree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(2)?;
+///
+/// tree.add(&[1.0, 2.0], 100)?;
+/// tree.add(&[2.0, 3.0], 101)?;
+/// tree.add(&[3.0, 4.0], 102)?;
+///
+/// let stats = tree.stats();
+///
+/// assert_eq!(stats.size, 3);
+/// assert_eq!(stats.stems, 1);
+/// assert_eq!(stats.leaves, 2);
+/// assert_eq!(stats.max_depth, 1);
+/// assert_eq!(stats.leaf_fill, vec![0, 1, 1]);
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeStats {
+    /// The number of elements in the tree
+    pub size: usize,
+    /// The number of stem (internal) nodes
+    pub stems: usize,
+    /// The number of leaf nodes
+    pub leaves: usize,
+    /// The depth of the shallowest leaf, where the root is at depth 0
+    pub min_depth: usize,
+    /// The depth of the deepest leaf
+    pub max_depth: usize,
+    /// The mean depth of the leaves
+    pub mean_depth: f64,
+    /// A histogram of leaf occupancy: `leaf_fill[n]` is the number of leaves holding
+    /// `n` elements, for `n` from zero up to the capacity per node
+    pub leaf_fill: Vec<usize>,
+    /// An estimate of the memory used by the tree's nodes and leaf buffers, in bytes.
+    /// Memory owned by the elements themselves is not included.
+    pub memory_bytes: usize,
+    /// The largest fraction of a stem's elements held by one of its children, over all
+    /// non-empty stems. 0.5 means that every stem is perfectly balanced, and 1.0 that
+    /// some stem has all of its elements on one side.
+    pub max_imbalance: f64,
+    /// The mean, over all non-empty stems, of the fraction of the stem's elements held
+    /// by its larger child
+    pub mean_imbalance: f64,
+}
+
+#[cfg(feature = "alloc")]
+impl TreeStats {
+    pub(crate) fn collect<A, T, const K: usize, O>(tree: &KdTree<A, T, K, O>) -> Self
+    where
+        A: Float + Zero + One + Signed,
+        T: PartialEq,
+        O: Ownership,
+    {
+        let mut stats = TreeStats {
+            size: tree.size(),
+            stems: 0,
+            leaves: 0,
+            min_depth: usize::MAX,
+            max_depth: 0,
+            mean_depth: 0.0,
+            leaf_fill: Vec::new(),
+            memory_bytes: 0,
+            max_imbalance: 0.5,
+            mean_imbalance: 0.0,
+        };
+        let mut total_depth = 0;
+        let mut total_imbalance = 0.0;
+        let mut non_empty_stems = 0;
+
+        tree.visit(|node, depth| {
+            stats.memory_bytes += core::mem::size_of::<KdTree<A, T, K, O>>();
+
+            match node.node() {
+                Node::Stem { left, right, .. } => {
+                    stats.stems += 1;
+
+                    if node.size() > 0 {
+                        let larger = left.size().max(right.size());
+                        let imbalance = larger as f64 / node.size() as f64;
+                        stats.max_imbalance = stats.max_imbalance.max(imbalance);
+                        total_imbalance += imbalance;
+                        non_empty_stems += 1;
+                    }
+                }
+                Node::Leaf {
+                    points,
+                    bucket,
+                    capacity,
+                } => {
+                    stats.leaves += 1;
+                    stats.min_depth = stats.min_depth.min(depth);
+                    stats.max_depth = stats.max_depth.max(depth);
+                    total_depth += depth;
+
+                    let slots = (*capacity).max(points.len()) + 1;
+                    if stats.leaf_fill.len() < slots {
+                        stats.leaf_fill.resize(slots, 0);
+                    }
+                    stats.leaf_fill[points.len()] += 1;
+
+                    stats.memory_bytes += points.capacity() * core::mem::size_of::<[A; K]>()
+                        + bucket.capacity() * core::mem::size_of::<T>();
+                }
+            }
+        });
+
+        stats.mean_depth = total_depth as f64 / stats.leaves as f64;
+        stats.mean_imbalance = if non_empty_stems > 0 {
+            total_imbalance / non_empty_stems as f64
+        } else {
+            0.5
+        };
+
+        stats
+    }
+}
+
+/// Counts of the work done by a single query, as returned by the `*_with_stats` query
+/// methods such as [`KdTree::nearest_with_stats`].
+///
+/// # Examples
+///
+/// ```rust
+/// use kiddo::KdTree;
+/// use kiddo::distance::squared_euclidean;
+///
+/// let mut tree: KdTree<f64, usize, 3> = KdTree::with_per_node_capacity(1)?;
+///
+/// tree.add(&[1.0, 2.0, 5.0], 100)?;
+/// tree.add(&[2.0, 3.0, 6.0], 101)?;
+/// tree.add(&[200.0, 300.0, 600.0], 102)?;
+///
+/// let (nearest, stats) = tree.nearest_with_stats(&[1.0, 2.0, 5.1], 1, &squared_euclidean)?;
+///
+/// assert_eq!(*nearest[0].1, 100);
+/// assert!(stats.pruned_subtrees > 0);
+/// assert!(stats.distance_evaluations < 3);
+/// # Ok::<(), kiddo::ErrorKind>(())
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// The number of stem (internal) nodes the query descended through
+    pub stems_visited: usize,
+    /// The number of leaves whose elements the query examined
+    pub leaves_visited: usize,
+    /// The number of times the query calculated the distance to an element
+    pub distance_evaluations: usize,
+    /// The number of subtrees the query skipped without visiting, because they were too
+    /// far from the query point to hold a result
+    pub pruned_subtrees: usize,
+}
@@ -1,5 +1,13 @@
+// Most of these helpers are only used by the trees that require `alloc`
+#![cfg_attr(not(feature = "alloc"), allow(dead_code))]
+
 use num_traits::Float;
 
+use crate::error::{ErrorKind, Operation};
+use crate::periodic::PeriodicBox;
+use crate::stats::QueryStats;
+use core::cell::Cell;
+
 pub fn distance_to_space<F, T, const K: usize>(
     p1: &[T; K],
     min_bounds: &[T; K],
@@ -23,11 +31,199 @@ where
     distance(p1, &p2)
 }
 
+pub fn distance_between_spaces<F, T, const K: usize>(
+    min_bounds_1: &[T; K],
+    max_bounds_1: &[T; K],
+    min_bounds_2: &[T; K],
+    max_bounds_2: &[T; K],
+    distance: &F,
+) -> T
+where
+    F: Fn(&[T; K], &[T; K]) -> T,
+    T: Float,
+{
+    let mut p1 = [T::zero(); K];
+    let mut p2 = [T::zero(); K];
+    for i in 0..K {
+        let (c1, c2) = closest_in_intervals(
+            min_bounds_1[i],
+            max_bounds_1[i],
+            min_bounds_2[i],
+            max_bounds_2[i],
+        );
+        p1[i] = c1;
+        p2[i] = c2;
+    }
+    distance(&p1, &p2)
+}
+
+// Converts a coordinate to `f64` for reporting in an error
+pub fn to_f64<T: Float>(value: T) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+// Returns the closest pair of values, one from each of two intervals
+pub fn closest_in_intervals<T: Float>(min_1: T, max_1: T, min_2: T, max_2: T) -> (T, T) {
+    if max_1 < min_2 {
+        (max_1, min_2)
+    } else if max_2 < min_1 {
+        (min_1, max_2)
+    } else {
+        let overlap = min_1.max(min_2);
+        (overlap, overlap)
+    }
+}
+
+// Checks that `point` is finite, and lies within `periodic` if it is given
+pub(crate) fn check_point<A: Float, const K: usize>(
+    point: &[A; K],
+    periodic: Option<&PeriodicBox<A, K>>,
+    operation: Operation,
+) -> Result<(), ErrorKind> {
+    // First check that point is finite
+    if let Some(axis) = point.iter().position(|n| !n.is_finite()) {
+        return Err(ErrorKind::NonFiniteCoordinate {
+            operation,
+            axis,
+            value: to_f64(point[axis]),
+        });
+    }
+
+    // Then check that point is in the bounds when periodic BCs are on
+    if let Some(periodic) = periodic {
+        periodic.check_contains(point, operation)?;
+    }
+
+    Ok(())
+}
+
+// Wraps `point` back into `periodic`, if it is given and configured to do so
+pub(crate) fn wrap_point<A: Float, const K: usize>(
+    point: &[A; K],
+    periodic: Option<&PeriodicBox<A, K>>,
+) -> [A; K] {
+    match periodic {
+        Some(periodic) if periodic.wrap_on_insert => periodic.wrap(point),
+        _ => *point,
+    }
+}
+
+// Chooses where to split a node with the given bounds: halfway along its widest
+// dimension. Returns `None` if the node has no width along any dimension.
+pub(crate) fn split_plane<A: Float, const K: usize>(
+    min_bounds: &[A; K],
+    max_bounds: &[A; K],
+) -> Option<(usize, A)> {
+    let mut split_dimension: Option<usize> = None;
+    let mut max = A::zero();
+    for dim in 0..K {
+        let diff = max_bounds[dim] - min_bounds[dim];
+        if !diff.is_nan() && diff > max {
+            max = diff;
+            split_dimension = Some(dim);
+        }
+    }
+
+    split_dimension.map(|dim| {
+        let min = min_bounds[dim];
+        let max = max_bounds[dim];
+        (dim, min + (max - min) / A::from(2.0_f64).unwrap())
+    })
+}
+
+/// A distance metric function, together with the periodic boundary conditions (if any)
+/// under which it is measured, and the counters (if any) that a query records its work in
+pub(crate) struct Metric<'a, A, F, const K: usize> {
+    distance: &'a F,
+    periodic: Option<PeriodicBox<A, K>>,
+    stats: Option<&'a Cell<QueryStats>>,
+}
+
+impl<'a, A: Float, F, const K: usize> Metric<'a, A, F, K>
+where
+    F: Fn(&[A; K], &[A; K]) -> A,
+{
+    pub fn new(distance: &'a F, periodic: Option<PeriodicBox<A, K>>) -> Self {
+        Metric {
+            distance,
+            periodic,
+            stats: None,
+        }
+    }
+
+    pub fn with_stats(self, stats: &'a Cell<QueryStats>) -> Self {
+        Metric {
+            stats: Some(stats),
+            ..self
+        }
+    }
+
+    #[inline(always)]
+    pub fn record<R>(&self, update: R)
+    where
+        R: FnOnce(&mut QueryStats),
+    {
+        if let Some(stats) = self.stats {
+            let mut current = stats.get();
+            update(&mut current);
+            stats.set(current);
+        }
+    }
+
+    // Checks a query point against the box that this metric measures distances through,
+    // which is either the tree's own or one given for a single query
+    pub fn check_point(&self, point: &[A; K], operation: Operation) -> Result<(), ErrorKind> {
+        check_point(point, self.periodic.as_ref(), operation)
+    }
+
+    pub fn distance(&self, a: &[A; K], b: &[A; K]) -> A {
+        self.record(|stats| stats.distance_evaluations += 1);
+
+        match &self.periodic {
+            None => (self.distance)(a, b),
+            Some(periodic) => periodic.distance(a, b, self.distance),
+        }
+    }
+
+    pub fn distance_to_space(&self, point: &[A; K], min_bounds: &[A; K], max_bounds: &[A; K]) -> A {
+        match &self.periodic {
+            None => distance_to_space(point, min_bounds, max_bounds, self.distance),
+            Some(periodic) => {
+                periodic.distance_to_space(point, min_bounds, max_bounds, self.distance)
+            }
+        }
+    }
+
+    pub fn distance_between_spaces(
+        &self,
+        min_bounds_1: &[A; K],
+        max_bounds_1: &[A; K],
+        min_bounds_2: &[A; K],
+        max_bounds_2: &[A; K],
+    ) -> A {
+        match &self.periodic {
+            None => distance_between_spaces(
+                min_bounds_1,
+                max_bounds_1,
+                min_bounds_2,
+                max_bounds_2,
+                self.distance,
+            ),
+            Some(periodic) => periodic.distance_between_spaces(
+                min_bounds_1,
+                max_bounds_1,
+                min_bounds_2,
+                max_bounds_2,
+                self.distance,
+            ),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::distance_to_space;
+    use super::{distance_between_spaces, distance_to_space};
     use crate::distance::squared_euclidean;
-    use std::f64::{INFINITY, NEG_INFINITY};
 
     #[test]
     fn test_normal_distance_to_space() {
@@ -40,7 +236,7 @@ mod tests {
         let dis = distance_to_space(
             &[0.0, 0.0],
             &[1.0, 1.0],
-            &[INFINITY, INFINITY],
+            &[f64::INFINITY, f64::INFINITY],
             &squared_euclidean,
         );
         assert_eq!(dis, 2.0);
@@ -50,8 +246,8 @@ mod tests {
     fn test_distance_inside_inf() {
         let dis = distance_to_space(
             &[2.0, 2.0],
-            &[NEG_INFINITY, NEG_INFINITY],
-            &[INFINITY, INFINITY],
+            &[f64::NEG_INFINITY, f64::NEG_INFINITY],
+            &[f64::INFINITY, f64::INFINITY],
             &squared_euclidean,
         );
         assert_eq!(dis, 0.0);
@@ -67,10 +263,34 @@ mod tests {
     fn distance_to_half_space() {
         let dis = distance_to_space(
             &[-2.0, 0.0],
-            &[0.0, NEG_INFINITY],
-            &[INFINITY, INFINITY],
+            &[0.0, f64::NEG_INFINITY],
+            &[f64::INFINITY, f64::INFINITY],
+            &squared_euclidean,
+        );
+        assert_eq!(dis, 4.0);
+    }
+
+    #[test]
+    fn test_distance_between_spaces() {
+        let dis = distance_between_spaces(
+            &[0.0, 0.0],
+            &[1.0, 1.0],
+            &[3.0, -1.0],
+            &[4.0, 0.5],
             &squared_euclidean,
         );
         assert_eq!(dis, 4.0);
     }
+
+    #[test]
+    fn test_distance_between_overlapping_spaces() {
+        let dis = distance_between_spaces(
+            &[0.0, 0.0],
+            &[2.0, 2.0],
+            &[1.0, 1.0],
+            &[3.0, 3.0],
+            &squared_euclidean,
+        );
+        assert_eq!(dis, 0.0);
+    }
 }
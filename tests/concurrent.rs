@@ -0,0 +1,133 @@
+#![cfg(feature = "std")]
+
+extern crate kiddo;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use kiddo::distance::squared_euclidean;
+use kiddo::ConcurrentKdTree;
+use kiddo::ErrorKind;
+use kiddo::KdTree;
+
+#[test]
+fn readers_see_consistent_snapshots_while_writing() {
+    let tree: Arc<ConcurrentKdTree<f64, usize, 2>> = Arc::new(ConcurrentKdTree::from(
+        KdTree::with_per_node_capacity(4).unwrap(),
+    ));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = Arc::clone(&tree);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let mut last_size = 0;
+                while !done.load(Ordering::SeqCst) {
+                    let snapshot = tree.snapshot();
+                    assert!(snapshot.size() >= last_size);
+                    last_size = snapshot.size();
+
+                    // Elements are added in order, so a snapshot holds a prefix of them
+                    let items: Vec<usize> = snapshot.iter().map(|(_, &item)| item).collect();
+                    assert_eq!(items.iter().max().map_or(0, |max| max + 1), items.len());
+
+                    if let Ok((dist, item)) = tree.nearest_one(&[0.0, 0.0], &squared_euclidean) {
+                        assert_eq!((dist, item), (0.0, 0));
+                    }
+                }
+            })
+        })
+        .collect();
+
+    // Hold on to a snapshot across modifications, which must not stop them sharing nodes
+    let early = tree.snapshot();
+    for idx in 0..1000 {
+        tree.add(&[(idx % 100) as f64, (idx / 100) as f64], idx)
+            .unwrap();
+    }
+    done.store(true, Ordering::SeqCst);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    assert_eq!(early.size(), 0);
+    assert_eq!(tree.size(), 1000);
+    assert_eq!(
+        tree.nearest(&[50.0, 5.1], 1, &squared_euclidean).unwrap()[0].1,
+        550
+    );
+    assert_eq!(
+        tree.within(&[50.0, 5.0], 1.0, &squared_euclidean)
+            .unwrap()
+            .len(),
+        5
+    );
+
+    assert_eq!(tree.remove(&[50.0, 5.0], &550).unwrap(), 1);
+    assert_eq!(tree.remove(&[50.0, 5.0], &550).unwrap(), 0);
+    assert_eq!(tree.size(), 999);
+    assert!(matches!(
+        tree.add(&[f64::NAN, 0.0], 1000),
+        Err(ErrorKind::NonFiniteCoordinate { axis: 0, .. })
+    ));
+
+    // A failed modification leaves the tree unchanged
+    tree.add(&[50.0, 5.0], 1000).unwrap();
+    tree.add(&[50.0, 5.0], 1001).unwrap();
+    let snapshot = tree.snapshot();
+    assert_eq!(snapshot.size(), 1001);
+    let within = snapshot
+        .within(&[50.0, 5.0], 0.0, &squared_euclidean)
+        .unwrap();
+    let mut items: Vec<usize> = within.into_iter().map(|(_, &item)| item).collect();
+    items.sort_unstable();
+    assert_eq!(items, vec![1000, 1001]);
+}
+
+static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+// An item that counts how many times it has been cloned
+#[derive(Debug, PartialEq)]
+struct Counted(usize);
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        CLONES.fetch_add(1, Ordering::SeqCst);
+        Counted(self.0)
+    }
+}
+
+#[test]
+fn held_snapshots_do_not_make_modifications_copy_the_tree() {
+    let tree: ConcurrentKdTree<f64, Counted, 2> =
+        ConcurrentKdTree::from(KdTree::with_per_node_capacity(4).unwrap());
+    for idx in 0..10_000 {
+        tree.add(&[(idx % 100) as f64, (idx / 100) as f64], Counted(idx))
+            .unwrap();
+    }
+
+    let snapshot = tree.snapshot();
+    CLONES.store(0, Ordering::SeqCst);
+    for idx in 0..100 {
+        tree.add(&[idx as f64 + 0.5, 50.5], Counted(10_000 + idx))
+            .unwrap();
+        assert_eq!(
+            tree.remove(&[idx as f64, 50.0], &Counted(5000 + idx))
+                .unwrap(),
+            1
+        );
+    }
+
+    // Each modification copies the elements of at most one leaf
+    assert!(CLONES.load(Ordering::SeqCst) <= 200 * 4);
+    assert_eq!(snapshot.size(), 10_000);
+    assert_eq!(tree.size(), 10_000);
+    assert_eq!(
+        snapshot
+            .nearest_one(&[0.0, 50.0], &squared_euclidean)
+            .unwrap(),
+        (0.0, &Counted(5000))
+    );
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "alloc")]
+
 extern crate kiddo;
 
 use kiddo::distance::squared_euclidean;
@@ -70,3 +72,53 @@ fn it_works() {
     iter.next().unwrap();
     assert_eq!(count.swap(0, Ordering::SeqCst), 0);
 }
+
+#[test]
+fn query_stats_match_counted_distance_calls() {
+    let mut kdtree = KdTree::with_per_node_capacity(1).unwrap();
+
+    let count = AtomicUsize::new(0);
+    let new_dist = |a: &[f64; 2], b: &[f64; 2]| {
+        count.fetch_add(1, Ordering::SeqCst);
+        squared_euclidean(a, b)
+    };
+
+    kdtree.add(&POINT_A.0, POINT_A.1).unwrap();
+    kdtree.add(&POINT_B.0, POINT_B.1).unwrap();
+    kdtree.add(&POINT_C.0, POINT_C.1).unwrap();
+    kdtree.add(&POINT_D.0, POINT_D.1).unwrap();
+
+    let (_, stats) = kdtree
+        .nearest_with_stats(&POINT_A.0, 1, &squared_euclidean)
+        .unwrap();
+    assert_eq!(stats.leaves_visited, 1);
+    assert_eq!(stats.distance_evaluations, 1);
+    assert_eq!(stats.stems_visited, 1);
+    assert!(stats.pruned_subtrees > 0);
+
+    let (_, stats) = kdtree
+        .nearest_with_stats(&POINT_A.0, 4, &squared_euclidean)
+        .unwrap();
+    assert_eq!(stats.leaves_visited, 4);
+    assert_eq!(stats.distance_evaluations, 4);
+    assert_eq!(stats.pruned_subtrees, 0);
+
+    let (nearest, stats) = kdtree
+        .nearest_one_with_stats(&POINT_D.0, &squared_euclidean)
+        .unwrap();
+    assert_eq!(*nearest.1, POINT_D.1);
+    assert_eq!(stats.distance_evaluations, stats.leaves_visited);
+
+    let (within, stats) = kdtree
+        .within_with_stats(&POINT_B.0, 2.0, &squared_euclidean)
+        .unwrap();
+    assert_eq!(within.len(), 3);
+    assert_eq!(stats.leaves_visited, 3);
+    assert_eq!(stats.distance_evaluations, 3);
+
+    // element distances are a subset of all metric calls, which also measure bounding boxes
+    let (_, stats) = kdtree.nearest_with_stats(&POINT_B.0, 2, &new_dist).unwrap();
+    let calls = count.swap(0, Ordering::SeqCst);
+    assert_eq!(stats.distance_evaluations, stats.leaves_visited);
+    assert!(stats.distance_evaluations <= calls);
+}
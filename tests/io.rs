@@ -0,0 +1,272 @@
+#![cfg(feature = "io")]
+
+extern crate kiddo;
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float32Array, Float64Array, Int32Array, RecordBatch};
+use kiddo::distance::squared_euclidean;
+use kiddo::ErrorKind;
+use kiddo::KdTree;
+use npyz::WriterBuilder;
+use parquet::arrow::ArrowWriter;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("kiddo-io-{}-{}", std::process::id(), name))
+}
+
+fn write_npy(name: &str, shape: &[u64], fortran: bool, values: &[f64]) -> PathBuf {
+    let path = temp_path(name);
+    let order = if fortran {
+        npyz::Order::Fortran
+    } else {
+        npyz::Order::C
+    };
+    let mut writer = npyz::WriteOptions::new()
+        .default_dtype()
+        .shape(shape)
+        .order(order)
+        .writer(File::create(&path).unwrap())
+        .begin_nd()
+        .unwrap();
+    writer.extend(values.iter().copied()).unwrap();
+    writer.finish().unwrap();
+    path
+}
+
+fn write_parquet(name: &str, columns: Vec<(&str, ArrayRef)>) -> PathBuf {
+    let path = temp_path(name);
+    let batch = RecordBatch::try_from_iter(columns).unwrap();
+    let mut writer =
+        ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+    path
+}
+
+fn assert_rows(tree: &KdTree<f64, usize, 2>, rows: &[[f64; 2]]) {
+    assert_eq!(tree.size(), rows.len());
+    for (idx, row) in rows.iter().enumerate() {
+        assert_eq!(
+            tree.nearest_one(row, &squared_euclidean).unwrap(),
+            (0.0, &idx)
+        );
+    }
+}
+
+// The capacity per node, as the number of leaf sizes counted by the tree's statistics
+fn capacity(tree: &KdTree<f64, usize, 2>) -> usize {
+    tree.stats().leaf_fill.len() - 1
+}
+
+const ROWS: [[f64; 2]; 3] = [[0.0, 1.0], [2.0, 3.0], [4.0, 5.0]];
+
+#[test]
+fn it_loads_npy_files() {
+    let path = write_npy("c.npy", &[3, 2], false, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_rows(&KdTree::from_npy(&path).unwrap(), &ROWS);
+    fs::remove_file(path).unwrap();
+
+    let path = write_npy(
+        "fortran.npy",
+        &[3, 2],
+        true,
+        &[0.0, 2.0, 4.0, 1.0, 3.0, 5.0],
+    );
+    assert_rows(&KdTree::from_npy(&path).unwrap(), &ROWS);
+    fs::remove_file(path).unwrap();
+
+    let path = write_npy("wide.npy", &[2, 3], false, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(
+        KdTree::<f64, usize, 2>::from_npy(&path).unwrap_err(),
+        ErrorKind::ShapeMismatch {
+            expected: 2,
+            found: 3
+        }
+    );
+    fs::remove_file(path).unwrap();
+
+    let path = write_npy("nan.npy", &[2, 2], false, &[0.0, 1.0, f64::NAN, 3.0]);
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_npy(&path),
+        Err(ErrorKind::InvalidFile { row: Some(1), .. })
+    ));
+    fs::remove_file(path).unwrap();
+
+    let path = write_npy("nan-fortran.npy", &[2, 2], true, &[0.0, 2.0, 1.0, f64::NAN]);
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_npy(&path),
+        Err(ErrorKind::InvalidFile { row: Some(1), .. })
+    ));
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        KdTree::<f64, usize, 2>::from_npy(temp_path("missing.npy")).unwrap_err(),
+        ErrorKind::Io(std::io::ErrorKind::NotFound)
+    );
+
+    // Headers claiming more rows than the file holds are rejected, rather than room being
+    // made for all of those rows
+    let path = write_npy("short.npy", &[3, 2], false, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    let data = fs::read(&path).unwrap();
+    let (magic, rest) = data.split_at(8);
+    let (header, values) = rest.split_at(rest.len() - 6 * 8);
+    let header = std::str::from_utf8(header).unwrap();
+    for rows in ["4", "1099511627776", "4611686018427387904"] {
+        // Drop as much padding as the shape grows by, so the values stay where they were
+        let shape = format!("({}, 2,", rows);
+        let padding = " ".repeat(shape.len() - "(3, 2,".len()) + "\n";
+        let header = header
+            .replacen("(3, 2,", &shape, 1)
+            .replacen(&padding, "\n", 1);
+        fs::write(&path, [magic, header.as_bytes(), values].concat()).unwrap();
+        assert!(matches!(
+            KdTree::<f64, usize, 2>::from_npy(&path),
+            Err(ErrorKind::InvalidFile { .. })
+        ));
+    }
+    fs::remove_file(path).unwrap();
+
+    let path = temp_path("truncated.npy");
+    fs::write(&path, b"\x93NUMPY").unwrap();
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_npy(&path),
+        Err(ErrorKind::InvalidFile { .. })
+    ));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn it_loads_files_with_a_capacity_and_periodic_box() {
+    let path = write_npy(
+        "capacity.npy",
+        &[3, 2],
+        false,
+        &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0],
+    );
+    let tree: KdTree<f64, usize, 2> = KdTree::from_npy_with_per_node_capacity(&path, 2).unwrap();
+    assert_eq!(capacity(&tree), 2);
+    assert_rows(&tree, &ROWS);
+    assert_eq!(
+        KdTree::<f64, usize, 2>::from_npy_with_per_node_capacity(&path, 0).unwrap_err(),
+        ErrorKind::ZeroCapacity
+    );
+
+    let tree: KdTree<f64, usize, 2> =
+        KdTree::from_npy_periodic_with_per_node_capacity(&path, 2, [10.0, 10.0]).unwrap();
+    assert_eq!(capacity(&tree), 2);
+    assert_eq!(
+        tree.nearest_one(&[9.5, 0.5], &squared_euclidean).unwrap(),
+        (0.5, &0)
+    );
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_npy_periodic_with_per_node_capacity(&path, 2, [3.0, 3.0]),
+        Err(ErrorKind::PeriodicOutOfBounds { .. })
+    ));
+    fs::remove_file(path).unwrap();
+
+    let path = temp_path("capacity.csv");
+    fs::write(&path, "0,1\n2,3\n4,5\n").unwrap();
+    let tree: KdTree<f64, usize, 2> =
+        KdTree::from_csv_periodic_with_per_node_capacity(&path, false, 2, [10.0, 10.0]).unwrap();
+    assert_eq!(capacity(&tree), 2);
+    assert_eq!(tree.periodic(), Some(&[10.0, 10.0].into()));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn it_loads_csv_files() {
+    let path = temp_path("points.csv");
+    fs::write(&path, "x,y\n0,1\n2.0, 3.0\n4,5e0\n").unwrap();
+    assert_rows(&KdTree::from_csv(&path, true).unwrap(), &ROWS);
+
+    let tree: KdTree<f32, usize, 2> = KdTree::from_csv(&path, true).unwrap();
+    assert_eq!(tree.size(), 3);
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_csv(&path, false),
+        Err(ErrorKind::InvalidFile { row: Some(1), .. })
+    ));
+
+    fs::write(&path, "0,1\n2,3,4\n").unwrap();
+    assert_eq!(
+        KdTree::<f64, usize, 2>::from_csv(&path, false).unwrap_err(),
+        ErrorKind::ShapeMismatch {
+            expected: 2,
+            found: 3
+        }
+    );
+
+    fs::write(&path, "0,1\n2,inf\n").unwrap();
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_csv(&path, false),
+        Err(ErrorKind::InvalidFile { row: Some(2), .. })
+    ));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn it_loads_parquet_files() {
+    let path = write_parquet(
+        "points.parquet",
+        vec![
+            (
+                "x",
+                Arc::new(Float64Array::from(vec![0.0, 2.0, 4.0])) as ArrayRef,
+            ),
+            (
+                "y",
+                Arc::new(Float32Array::from(vec![1.0, 3.0, 5.0])) as ArrayRef,
+            ),
+        ],
+    );
+    assert_rows(&KdTree::from_parquet(&path).unwrap(), &ROWS);
+    let tree: KdTree<f64, usize, 2> =
+        KdTree::from_parquet_with_per_node_capacity(&path, 1).unwrap();
+    assert_eq!(capacity(&tree), 1);
+    assert_rows(&tree, &ROWS);
+    assert_eq!(
+        KdTree::<f64, usize, 3>::from_parquet(&path).unwrap_err(),
+        ErrorKind::ShapeMismatch {
+            expected: 3,
+            found: 2
+        }
+    );
+    fs::remove_file(path).unwrap();
+
+    let path = write_parquet(
+        "nulls.parquet",
+        vec![
+            (
+                "x",
+                Arc::new(Float64Array::from(vec![Some(0.0), None])) as ArrayRef,
+            ),
+            (
+                "y",
+                Arc::new(Float64Array::from(vec![1.0, 3.0])) as ArrayRef,
+            ),
+        ],
+    );
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_parquet(&path),
+        Err(ErrorKind::InvalidFile { row: Some(1), .. })
+    ));
+    fs::remove_file(path).unwrap();
+
+    let path = write_parquet(
+        "integers.parquet",
+        vec![
+            ("x", Arc::new(Int32Array::from(vec![0, 2])) as ArrayRef),
+            (
+                "y",
+                Arc::new(Float64Array::from(vec![1.0, 3.0])) as ArrayRef,
+            ),
+        ],
+    );
+    assert!(matches!(
+        KdTree::<f64, usize, 2>::from_parquet(&path),
+        Err(ErrorKind::InvalidFile { row: None, .. })
+    ));
+    fs::remove_file(path).unwrap();
+}
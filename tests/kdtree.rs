@@ -1,8 +1,13 @@
+#![cfg(feature = "alloc")]
+#![allow(clippy::needless_range_loop)]
+
 extern crate kiddo;
 
 use kiddo::distance::squared_euclidean;
 use kiddo::ErrorKind;
 use kiddo::KdTree;
+use kiddo::Operation;
+use kiddo::PeriodicBox;
 
 static POINT_A: ([f64; 2], usize) = ([0f64, 0f64], 0);
 static POINT_B: ([f64; 2], usize) = ([1f64, 1f64], 1);
@@ -92,6 +97,604 @@ fn it_works() {
     );
 }
 
+#[test]
+fn it_returns_points_with_results() {
+    let capacity_per_node = 2;
+    let mut kdtree = KdTree::with_per_node_capacity(capacity_per_node).unwrap();
+
+    kdtree.add(&POINT_A.0, POINT_A.1).unwrap();
+    kdtree.add(&POINT_B.0, POINT_B.1).unwrap();
+    kdtree.add(&POINT_C.0, POINT_C.1).unwrap();
+    kdtree.add(&POINT_D.0, POINT_D.1).unwrap();
+
+    assert_eq!(
+        kdtree
+            .nearest_with_point(&POINT_A.0, 2, &squared_euclidean)
+            .unwrap(),
+        vec![(0f64, &POINT_A.0, &0), (2f64, &POINT_B.0, &1)]
+    );
+
+    assert_eq!(
+        kdtree
+            .within_with_point(&POINT_B.0, 1.0, &squared_euclidean)
+            .unwrap(),
+        vec![(0f64, &POINT_B.0, &1)]
+    );
+
+    assert_eq!(
+        kdtree
+            .iter_nearest_with_point(&POINT_A.0, &squared_euclidean)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![
+            (0f64, &POINT_A.0, &0),
+            (2f64, &POINT_B.0, &1),
+            (8f64, &POINT_C.0, &2),
+            (18f64, &POINT_D.0, &3)
+        ]
+    );
+
+    let mut best = kdtree
+        .best_n_within_with_point(&POINT_D.0, 8.0, 2, &squared_euclidean)
+        .unwrap();
+    best.sort_by(|a, b| a.2.cmp(b.2));
+    assert_eq!(best, vec![(8f64, &POINT_B.0, &1), (2f64, &POINT_C.0, &2)]);
+}
+
+#[test]
+fn searcher_matches_tree_queries() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    for i in 0..200 {
+        kdtree.add(&rand::random::<[f64; 2]>(), i).unwrap();
+    }
+
+    let mut searcher = kdtree.searcher();
+    for _ in 0..50 {
+        let query = rand::random::<[f64; 2]>();
+
+        assert_eq!(
+            searcher.nearest(&query, 5, &squared_euclidean).unwrap(),
+            kdtree
+                .nearest(&query, 5, &squared_euclidean)
+                .unwrap()
+                .as_slice()
+        );
+        assert_eq!(
+            searcher.nearest_one(&query, &squared_euclidean).unwrap(),
+            kdtree.nearest_one(&query, &squared_euclidean).unwrap()
+        );
+        assert_eq!(
+            searcher.within(&query, 0.05, &squared_euclidean).unwrap(),
+            kdtree
+                .within(&query, 0.05, &squared_euclidean)
+                .unwrap()
+                .as_slice()
+        );
+
+        let mut unsorted = searcher
+            .within_unsorted(&query, 0.05, &squared_euclidean)
+            .unwrap()
+            .to_vec();
+        unsorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        assert_eq!(
+            unsorted,
+            kdtree.within(&query, 0.05, &squared_euclidean).unwrap()
+        );
+    }
+}
+
+#[test]
+fn nearest_n_matches_nearest() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+
+    assert_eq!(
+        kdtree.nearest_n::<_, 0>(&[0.5, 0.5], &squared_euclidean),
+        Ok([])
+    );
+    assert_eq!(
+        kdtree.nearest_n::<_, 1>(&[0.5, 0.5], &squared_euclidean),
+        Err(ErrorKind::Empty)
+    );
+
+    for i in 0..200 {
+        kdtree.add(&rand::random::<[f64; 2]>(), i).unwrap();
+    }
+
+    for _ in 0..50 {
+        let query = rand::random::<[f64; 2]>();
+
+        assert_eq!(
+            kdtree
+                .nearest_n::<_, 5>(&query, &squared_euclidean)
+                .unwrap()
+                .to_vec(),
+            kdtree.nearest(&query, 5, &squared_euclidean).unwrap()
+        );
+        assert_eq!(
+            kdtree
+                .nearest_n::<_, 1>(&query, &squared_euclidean)
+                .unwrap()[0],
+            kdtree.nearest_one(&query, &squared_euclidean).unwrap()
+        );
+    }
+}
+
+#[test]
+fn nearest_within_respects_count_and_radius() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+
+    assert_eq!(
+        kdtree.nearest_within(&[0.5, 0.5], 5, 0.1, &squared_euclidean),
+        Ok(vec![])
+    );
+
+    for i in 0..200 {
+        kdtree.add(&rand::random::<[f64; 2]>(), i).unwrap();
+    }
+
+    for _ in 0..50 {
+        let query = rand::random::<[f64; 2]>();
+
+        for &(num, radius) in &[(5, 0.01), (5, 1.0), (50, 0.05), (0, 1.0)] {
+            let mut expected = kdtree.within(&query, radius, &squared_euclidean).unwrap();
+            expected.truncate(num);
+
+            assert_eq!(
+                kdtree
+                    .nearest_within(&query, num, radius, &squared_euclidean)
+                    .unwrap(),
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn best_n_within_by_key_handles_non_copy_items() {
+    let mut kdtree: KdTree<f64, String, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    let mut points = vec![];
+
+    for i in 0..200 {
+        let point = rand::random::<[f64; 2]>();
+        kdtree.add(&point, format!("{:03}", i)).unwrap();
+        points.push((point, format!("{:03}", i)));
+    }
+
+    for _ in 0..50 {
+        let query = rand::random::<[f64; 2]>();
+
+        let mut expected: Vec<&String> = points
+            .iter()
+            .filter(|(p, _)| squared_euclidean(p, &query) <= 0.1)
+            .map(|(_, id)| id)
+            .collect();
+        expected.sort_by(|a, b| b.cmp(a));
+        expected.truncate(5);
+
+        let best = kdtree
+            .best_n_within_by_key(&query, 0.1, 5, &squared_euclidean, |id| {
+                std::cmp::Reverse(id.clone())
+            })
+            .unwrap();
+
+        assert_eq!(best.iter().map(|x| x.1).collect::<Vec<_>>(), expected);
+        assert!(best.iter().all(|x| x.0 <= 0.1));
+    }
+}
+
+#[test]
+fn nearest_filtered_skips_rejected_items() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+
+    for i in 0..200 {
+        kdtree.add(&rand::random::<[f64; 2]>(), i).unwrap();
+    }
+
+    let is_even = |&item: &usize| item % 2 == 0;
+
+    for _ in 0..50 {
+        let query = rand::random::<[f64; 2]>();
+
+        let mut expected = kdtree.nearest(&query, 200, &squared_euclidean).unwrap();
+        expected.retain(|x| is_even(x.1));
+
+        assert_eq!(
+            kdtree
+                .nearest_filtered(&query, 5, &squared_euclidean, is_even)
+                .unwrap(),
+            &expected[..5]
+        );
+        assert_eq!(
+            kdtree
+                .nearest_one_filtered(&query, &squared_euclidean, is_even)
+                .unwrap(),
+            expected[0]
+        );
+    }
+
+    assert_eq!(
+        kdtree.nearest_one_filtered(&[0.5, 0.5], &squared_euclidean, |_| false),
+        Err(ErrorKind::Empty)
+    );
+    assert_eq!(
+        kdtree.nearest_filtered(&[0.5, 0.5], 5, &squared_euclidean, |_| false),
+        Ok(vec![])
+    );
+}
+
+#[test]
+fn knn_graph_excludes_self() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    let mut points = vec![];
+
+    for i in 0..200 {
+        let point = rand::random::<[f64; 2]>();
+        kdtree.add(&point, i).unwrap();
+        points.push(point);
+    }
+
+    let mut expected: Vec<Vec<(f64, usize)>> = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let mut row: Vec<(f64, usize)> = points
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(j, q)| (squared_euclidean(p, q), j))
+                .collect();
+            row.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            row.truncate(5);
+            row
+        })
+        .collect();
+
+    let graph = kdtree.knn_graph(5, &squared_euclidean).unwrap();
+    let mut actual: Vec<Vec<(f64, usize)>> = graph
+        .iter()
+        .map(|row| row.iter().map(|&(d, &j)| (d, j)).collect())
+        .collect();
+
+    expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    actual.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(actual, expected);
+
+    #[cfg(feature = "parallel")]
+    assert_eq!(kdtree.par_knn_graph(5, &squared_euclidean).unwrap(), graph);
+}
+
+#[test]
+fn join_matches_per_point_queries() {
+    let mut tree_a: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    let mut tree_b: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    let mut points_b = vec![];
+
+    for i in 0..300 {
+        tree_a.add(&rand::random::<[f64; 2]>(), i).unwrap();
+    }
+    for i in 0..200 {
+        let point = rand::random::<[f64; 2]>();
+        tree_b.add(&point, i).unwrap();
+        points_b.push(point);
+    }
+
+    let joined = tree_a.join_nearest(&tree_b, 3, &squared_euclidean).unwrap();
+    assert_eq!(joined.len(), 200);
+    for (&i, neighbours) in joined.iter() {
+        assert_eq!(
+            *neighbours,
+            tree_a.nearest(&points_b[i], 3, &squared_euclidean).unwrap()
+        );
+    }
+
+    let joined = tree_a
+        .join_within(&tree_b, 0.01, &squared_euclidean)
+        .unwrap();
+    assert_eq!(joined.len(), 200);
+    for (&i, neighbours) in joined.iter() {
+        assert_eq!(
+            *neighbours,
+            tree_a
+                .within(&points_b[i], 0.01, &squared_euclidean)
+                .unwrap()
+        );
+    }
+
+    let empty: KdTree<f64, usize, 2> = KdTree::new();
+    assert!(tree_a
+        .join_nearest(&empty, 3, &squared_euclidean)
+        .unwrap()
+        .is_empty());
+    assert!(empty
+        .join_nearest(&tree_b, 3, &squared_euclidean)
+        .unwrap()
+        .iter()
+        .all(|(_, neighbours)| neighbours.is_empty()));
+}
+
+#[test]
+fn stats_describe_tree_shape() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    let stats = kdtree.stats();
+    assert_eq!((stats.stems, stats.leaves, stats.max_depth), (0, 1, 0));
+    assert_eq!(stats.leaf_fill, vec![1, 0, 0, 0, 0]);
+
+    for idx in 0..200 {
+        kdtree.add(&[idx as f64, (idx % 7) as f64], idx).unwrap();
+    }
+    let stats = kdtree.stats();
+
+    let mut leaf_items = 0;
+    let mut depths = vec![];
+    kdtree.visit(|node, depth| {
+        if node.is_leaf() {
+            leaf_items += node.size();
+            depths.push(depth);
+        } else {
+            for axis in 0..2 {
+                assert!(node.min_bounds()[axis] <= node.max_bounds()[axis]);
+            }
+        }
+    });
+
+    assert_eq!(stats.size, 200);
+    assert_eq!(leaf_items, 200);
+    assert_eq!(stats.leaves, depths.len());
+    assert_eq!(stats.stems, stats.leaves - 1);
+    assert_eq!(stats.min_depth, *depths.iter().min().unwrap());
+    assert_eq!(stats.max_depth, *depths.iter().max().unwrap());
+    assert_eq!(stats.leaf_fill.iter().sum::<usize>(), stats.leaves);
+    assert_eq!(
+        stats
+            .leaf_fill
+            .iter()
+            .enumerate()
+            .map(|(n, count)| n * count)
+            .sum::<usize>(),
+        200
+    );
+    assert!(
+        stats.mean_depth >= stats.min_depth as f64 && stats.mean_depth <= stats.max_depth as f64
+    );
+    assert!(stats.mean_imbalance >= 0.5 && stats.mean_imbalance <= stats.max_imbalance);
+    assert!(stats.max_imbalance <= 1.0);
+    assert!(stats.memory_bytes >= 200 * std::mem::size_of::<([f64; 2], usize)>());
+}
+
+#[test]
+fn iterators_visit_every_element() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    assert_eq!(kdtree.iter().next(), None);
+
+    for idx in 0..100 {
+        kdtree
+            .add(&[(idx * 37 % 100) as f64, (idx % 7) as f64], idx)
+            .unwrap();
+    }
+    kdtree.remove(&[37.0, 1.0], &1).unwrap();
+
+    let iter = kdtree.iter();
+    assert_eq!(iter.len(), 99);
+    let mut items: Vec<usize> = iter.map(|(_, &item)| item).collect();
+    items.sort_unstable();
+    assert_eq!(items, (0..100).filter(|&idx| idx != 1).collect::<Vec<_>>());
+
+    for (point, &item) in &kdtree {
+        assert_eq!(*point, [(item * 37 % 100) as f64, (item % 7) as f64]);
+    }
+    assert!(kdtree.points().eq(kdtree.iter().map(|(point, _)| point)));
+
+    for (_, item) in &mut kdtree {
+        *item *= 2;
+    }
+    let nearest = kdtree
+        .nearest_one(&[74.0, 2.0], &squared_euclidean)
+        .unwrap();
+    assert_eq!(*nearest.1, 4);
+
+    let expected: Vec<([f64; 2], usize)> = kdtree.iter().map(|(&p, &d)| (p, d)).collect();
+    let owned: Vec<([f64; 2], usize)> = kdtree.into_iter().collect();
+    assert_eq!(owned, expected);
+}
+
+#[test]
+fn mutable_queries_match_immutable_queries() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(3).unwrap();
+    assert_eq!(
+        kdtree.nearest_one_mut(&POINT_A.0, &squared_euclidean),
+        Err(ErrorKind::Empty)
+    );
+    assert!(kdtree
+        .within_mut(&POINT_A.0, 1.0, &squared_euclidean)
+        .unwrap()
+        .is_empty());
+
+    for idx in 0..100 {
+        kdtree
+            .add(&[(idx * 37 % 100) as f64, (idx % 7) as f64], idx)
+            .unwrap();
+        kdtree
+            .add(&[(idx * 37 % 100) as f64, (idx % 7) as f64], idx + 100)
+            .unwrap();
+    }
+
+    for query in [[0.0, 0.0], [50.5, 3.2], [99.0, 6.0], [12.0, -4.0]] {
+        let (expected_dist, &expected) = kdtree.nearest_one(&query, &squared_euclidean).unwrap();
+        let (dist, item) = kdtree.nearest_one_mut(&query, &squared_euclidean).unwrap();
+        assert_eq!((dist, *item), (expected_dist, expected));
+        *item += 1000;
+        assert_eq!(
+            *kdtree.nearest_one(&query, &squared_euclidean).unwrap().1,
+            expected + 1000
+        );
+
+        let expected: Vec<(f64, usize)> = kdtree
+            .within(&query, 30.0, &squared_euclidean)
+            .unwrap()
+            .into_iter()
+            .map(|(dist, &item)| (dist, item))
+            .collect();
+        let within = kdtree.within_mut(&query, 30.0, &squared_euclidean).unwrap();
+        let dists: Vec<f64> = within.iter().map(|(dist, _)| *dist).collect();
+        assert_eq!(
+            dists,
+            expected.iter().map(|(dist, _)| *dist).collect::<Vec<_>>()
+        );
+
+        let mut items: Vec<usize> = within.into_iter().map(|(_, item)| *item).collect();
+        let mut expected: Vec<usize> = expected.into_iter().map(|(_, item)| item).collect();
+        items.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(items, expected);
+    }
+
+    assert_eq!(
+        kdtree
+            .within_mut(&[f64::NAN, 0.0], 1.0, &squared_euclidean)
+            .unwrap_err()
+            .axis(),
+        Some(0)
+    );
+}
+
+#[test]
+fn merge_and_extend_match_adding_every_element() {
+    let entries: Vec<([f64; 2], usize)> = (0..300)
+        .map(|idx| ([(idx * 37 % 100) as f64, (idx * 13 % 29) as f64], idx))
+        .collect();
+    let mut expected: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    for (point, data) in &entries {
+        expected.add(point, *data).unwrap();
+    }
+
+    // `other` is small enough to be added to the first tree, and large enough to rebuild it
+    for split_at in [280, 150, 0] {
+        let mut tree_1: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+        let mut tree_2: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(9).unwrap();
+        tree_1.extend(entries[..split_at].iter().cloned());
+        tree_2.extend(entries[split_at..].iter().cloned());
+
+        let merged = tree_1.merge(tree_2).unwrap();
+        assert_eq!(merged.size(), 300);
+        assert_eq!(merged.stats().leaf_fill.len(), 5);
+
+        let mut items: Vec<usize> = merged.iter().map(|(_, &item)| item).collect();
+        items.sort_unstable();
+        assert_eq!(items, (0..300).collect::<Vec<_>>());
+
+        for query in [[0.0, 0.0], [50.5, 3.2], [99.0, 28.0], [12.0, -4.0]] {
+            let dists = |tree: &KdTree<f64, usize, 2>| -> Vec<f64> {
+                let nearest = tree.nearest(&query, 10, &squared_euclidean).unwrap();
+                nearest.into_iter().map(|(dist, _)| dist).collect()
+            };
+            assert_eq!(dists(&merged), dists(&expected));
+        }
+    }
+
+    let periodic = PeriodicBox::new([0.0, 0.0], [10.0, 10.0]);
+    let mut tree_1: KdTree<f64, usize, 2> = KdTree::new_periodic(periodic);
+    tree_1.add(&[1.0, 1.0], 0).unwrap();
+    let mut tree_2: KdTree<f64, usize, 2> = KdTree::new();
+    tree_2.extend(vec![([2.0, 2.0], 1), ([12.0, 2.0], 2)]);
+    let (error, tree_1, tree_2) = tree_1.merge(tree_2).unwrap_err();
+    assert_eq!(
+        error,
+        ErrorKind::PeriodicOutOfBounds {
+            operation: Operation::Add,
+            axis: 0,
+            value: 12.0,
+            lower: 0.0,
+            upper: 10.0,
+        }
+    );
+    // Neither tree loses its elements when the merge fails
+    assert_eq!(tree_1.iter().collect::<Vec<_>>(), vec![(&[1.0, 1.0], &0)]);
+    assert_eq!(
+        tree_2.iter().collect::<Vec<_>>(),
+        vec![(&[2.0, 2.0], &1), (&[12.0, 2.0], &2)]
+    );
+    assert_eq!(tree_1.periodic(), Some(&periodic));
+
+    let mut tree_1: KdTree<f64, usize, 2> = KdTree::new_periodic(periodic.with_wrapping(true));
+    tree_1.add(&[1.0, 1.0], 0).unwrap();
+    let mut tree_2: KdTree<f64, usize, 2> = KdTree::new();
+    tree_2.add(&[12.0, 2.0], 1).unwrap();
+    let merged = tree_1.merge(tree_2).unwrap();
+    assert_eq!(
+        merged.points().collect::<Vec<_>>(),
+        vec![&[1.0, 1.0], &[2.0, 2.0]]
+    );
+}
+
+#[test]
+fn partition_splits_tree_into_shards() {
+    let empty: KdTree<f64, usize, 2> = KdTree::new();
+    assert!(empty.partition(4).is_empty());
+
+    let build = || {
+        let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+        for idx in 0..500 {
+            kdtree
+                .add(&[(idx * 37 % 101) as f64, (idx * 13 % 29) as f64], idx)
+                .unwrap();
+        }
+        kdtree
+    };
+    let leaves = build().stats().leaves;
+
+    assert!(build().partition(0).is_empty());
+
+    for n in [1, 2, 5, 16, leaves + 10] {
+        let shards = build().partition(n);
+        if n < leaves {
+            assert_eq!(shards.len(), n);
+        } else {
+            assert!(shards.len() <= n);
+            assert!(shards.iter().all(|shard| shard.stats().leaves == 1));
+        }
+
+        let mut items = vec![];
+        for shard in &shards {
+            assert!(shard.size() > 0);
+            for (point, &item) in shard {
+                for axis in 0..2 {
+                    assert!(shard.min_bounds()[axis] <= point[axis]);
+                    assert!(point[axis] <= shard.max_bounds()[axis]);
+                }
+                items.push(item);
+            }
+        }
+        items.sort_unstable();
+        assert_eq!(items, (0..500).collect::<Vec<_>>());
+    }
+
+    // Almost every element is bunched up at one end, so halving the bounds puts nearly all
+    // of them on one side of each stem
+    let mut skewed: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    for idx in 0..500 {
+        let x = if idx < 10 {
+            1000.0 * idx as f64
+        } else {
+            idx as f64 / 1000.0
+        };
+        skewed.add(&[x, (idx % 7) as f64], idx).unwrap();
+    }
+    for n in [2, 4, 8] {
+        let shards = skewed.clone().partition(n);
+        assert_eq!(shards.len(), n);
+        let sizes: Vec<usize> = shards.iter().map(|shard| shard.size()).collect();
+        assert_eq!(sizes.iter().sum::<usize>(), 500);
+        assert!(sizes.iter().all(|&size| size * n >= 500 / 2), "{:?}", sizes);
+    }
+}
+
+#[test]
+#[should_panic(expected = "non-finite coordinate")]
+fn extend_panics_on_invalid_elements() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::new();
+    kdtree.extend(vec![([0.0, 0.0], 0), ([f64::NAN, 0.0], 1)]);
+}
+
 #[test]
 fn nearest_works_with_larger_example() {
     let vertices = vec![
@@ -164,25 +767,44 @@ fn nearest_one_works_with_larger_example() {
 
 #[test]
 fn handles_non_finite_coordinate() {
-    let point_a = ([std::f64::NAN, std::f64::NAN], 0f64);
-    let point_b = ([std::f64::INFINITY, std::f64::INFINITY], 0f64);
+    let point_a = ([f64::NAN, f64::NAN], 0f64);
+    let point_b = ([f64::INFINITY, f64::INFINITY], 0f64);
     let mut kdtree = KdTree::with_per_node_capacity(1).unwrap();
 
-    assert_eq!(
+    assert!(matches!(
         kdtree.add(&point_a.0, point_a.1),
-        Err(ErrorKind::NonFiniteCoordinate)
-    );
+        Err(ErrorKind::NonFiniteCoordinate { operation: Operation::Add, axis: 0, value }) if value.is_nan()
+    ));
     assert_eq!(
         kdtree.add(&point_b.0, point_b.1),
-        Err(ErrorKind::NonFiniteCoordinate)
+        Err(ErrorKind::NonFiniteCoordinate {
+            operation: Operation::Add,
+            axis: 0,
+            value: f64::INFINITY
+        })
     );
     assert_eq!(
         kdtree.nearest(&point_b.0, 1, &squared_euclidean),
-        Err(ErrorKind::NonFiniteCoordinate)
+        Err(ErrorKind::NonFiniteCoordinate {
+            operation: Operation::Query,
+            axis: 0,
+            value: f64::INFINITY
+        })
     );
-    assert_eq!(
+    assert!(matches!(
         kdtree.nearest(&point_a.0, 1, &squared_euclidean),
-        Err(ErrorKind::NonFiniteCoordinate)
+        Err(ErrorKind::NonFiniteCoordinate {
+            operation: Operation::Query,
+            ..
+        })
+    ));
+    assert_eq!(
+        kdtree.remove(&[0.0, f64::NEG_INFINITY], &0f64),
+        Err(ErrorKind::NonFiniteCoordinate {
+            operation: Operation::Remove,
+            axis: 1,
+            value: f64::NEG_INFINITY
+        })
     );
 }
 
@@ -334,7 +956,7 @@ fn handles_remove_correctly() {
     kdtree.add(&item4.0, item4.1).unwrap();
     kdtree.add(&item5.0, item5.1).unwrap();
 
-    let num_removed = kdtree.remove(&&item3.0, &item3.1).unwrap();
+    let num_removed = kdtree.remove(&item3.0, &item3.1).unwrap();
     assert_eq!(kdtree.size(), 4);
     assert_eq!(num_removed, 1);
     assert_eq!(
@@ -360,7 +982,7 @@ fn handles_remove_multiple_match() {
     kdtree.add(&item4.0, item4.1).unwrap();
 
     assert_eq!(kdtree.size(), 4);
-    let num_removed = kdtree.remove(&&[0f64], &1).unwrap();
+    let num_removed = kdtree.remove(&[0f64], &1).unwrap();
     assert_eq!(kdtree.size(), 2);
     assert_eq!(num_removed, 2);
     assert_eq!(
@@ -385,7 +1007,7 @@ fn handles_remove_no_match() {
     kdtree.add(&item3.0, item3.1).unwrap();
     kdtree.add(&item4.0, item4.1).unwrap();
 
-    let num_removed = kdtree.remove(&&[1f64], &2).unwrap();
+    let num_removed = kdtree.remove(&[1f64], &2).unwrap();
     assert_eq!(kdtree.size(), 4);
     assert_eq!(num_removed, 0);
     assert_eq!(
@@ -396,9 +1018,37 @@ fn handles_remove_no_match() {
 
 #[test]
 fn error_messages_do_not_overflow_stack() {
-    format!("{}", ErrorKind::NonFiniteCoordinate);
-    format!("{}", ErrorKind::ZeroCapacity);
-    format!("{}", ErrorKind::Empty);
+    let _ = format!(
+        "{}",
+        ErrorKind::NonFiniteCoordinate {
+            operation: Operation::Add,
+            axis: 0,
+            value: f64::NAN
+        }
+    );
+    let _ = format!("{}", ErrorKind::ZeroCapacity);
+    let _ = format!("{}", ErrorKind::Empty);
+    assert_eq!(
+        format!(
+            "{}",
+            ErrorKind::PeriodicOutOfBounds {
+                operation: Operation::Remove,
+                axis: 1,
+                value: 11.5,
+                lower: 0.0,
+                upper: 10.0
+            }
+        ),
+        "KdTree error: remove rejected: coordinate 11.5 on axis 1 is outside the periodic bounds [0, 10]"
+    );
+    let _ = format!(
+        "{}",
+        ErrorKind::InvalidPeriodicBox {
+            axis: 0,
+            lower: 0.0,
+            upper: 0.0
+        }
+    );
 }
 
 #[test]
@@ -449,19 +1099,19 @@ fn test_periodic_1d_nearest() {
     let knns: Vec<(f64, &usize)> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.nearest_one_periodic(&q, &squared_euclidean, p).unwrap()
+            t.nearest_one_periodic(q, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
     for (query_index, q) in query.iter().enumerate() {
         
         // Initialize neighbor
-        let mut neighbor = (std::f64::MAX, std::usize::MAX);
+        let mut neighbor = (f64::MAX, usize::MAX);
 
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -475,13 +1125,13 @@ fn test_periodic_1d_nearest() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -495,7 +1145,7 @@ fn test_periodic_1d_nearest() {
 
         // Check that brute force result agrees with KdTree result
         assert_eq!(&neighbor.1, knns[query_index].1, "{} {}", neighbor.0, knns[query_index].0);
-        assert!((neighbor.0 - knns[query_index].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index].0);
+        assert!((neighbor.0 - knns[query_index].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index].0);
     }
 }
 
@@ -548,19 +1198,19 @@ fn test_periodic_2d_nearest() {
     let knns: Vec<(f64, &usize)> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.nearest_one_periodic(&q, &squared_euclidean, p).unwrap()
+            t.nearest_one_periodic(q, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
     for (query_index, q) in query.iter().enumerate() {
         
         // Initialize neighbor
-        let mut neighbor = (std::f64::MAX, std::usize::MAX);
+        let mut neighbor = (f64::MAX, usize::MAX);
 
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -574,13 +1224,13 @@ fn test_periodic_2d_nearest() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -594,9 +1244,9 @@ fn test_periodic_2d_nearest() {
 
         // Check that brute force result agrees with KdTree result
         assert_eq!(&neighbor.1, knns[query_index].1, "{} {}", neighbor.0, knns[query_index].0);
-        assert!((neighbor.0 - knns[query_index].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index].0);
+        assert!((neighbor.0 - knns[query_index].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index].0);
     }
-=======
+}
 
 #[test]
 fn test_periodic_squared_euclidean_1d_f64(){
@@ -635,7 +1285,7 @@ fn test_periodic_squared_euclidean_topbottom_2d_f64(){
     let expected = 2.0 * 2.0;
 
     assert!((result-expected).abs() < F64_TOLERANCE);
-
+}
 
 #[test]
 fn test_periodic_3d_nearest() {
@@ -685,19 +1335,19 @@ fn test_periodic_3d_nearest() {
     let knns: Vec<(f64, &usize)> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.nearest_one_periodic(&q, &squared_euclidean, p).unwrap()
+            t.nearest_one_periodic(q, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
     for (query_index, q) in query.iter().enumerate() {
         
         // Initialize neighbor
-        let mut neighbor = (std::f64::MAX, std::usize::MAX);
+        let mut neighbor = (f64::MAX, usize::MAX);
 
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -711,13 +1361,13 @@ fn test_periodic_3d_nearest() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -731,7 +1381,7 @@ fn test_periodic_3d_nearest() {
 
         // Check that brute force result agrees with KdTree result
         assert_eq!(&neighbor.1, knns[query_index].1, "{} {}", neighbor.0, knns[query_index].0);
-        assert!((neighbor.0 - knns[query_index].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index].0);
+        assert!((neighbor.0 - knns[query_index].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index].0);
     }
 }
 
@@ -789,19 +1439,19 @@ fn test_periodic_1d_nearest_n() {
     let knns: Vec<Vec<(f64, &usize)>> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.nearest_periodic(&q, N, &squared_euclidean, p).unwrap()
+            t.nearest_periodic(q, N, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
     for (query_index, q) in query.iter().enumerate() {
         
         // Initialize neighbor
-        let mut neighbors = [(std::f64::MAX, std::usize::MAX); N];
+        let mut neighbors = [(f64::MAX, usize::MAX); N];
 
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -815,13 +1465,13 @@ fn test_periodic_1d_nearest_n() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -837,7 +1487,7 @@ fn test_periodic_1d_nearest_n() {
         // Check that brute force result agrees with KdTree result
         for (k, neighbor) in neighbors.iter().enumerate() {
             assert_eq!(&neighbor.1, knns[query_index][k].1, "{} {}", neighbor.0, knns[query_index][k].0);
-            assert!((neighbor.0 - knns[query_index][k].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
+            assert!((neighbor.0 - knns[query_index][k].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
         }
     }
 }
@@ -894,19 +1544,19 @@ fn test_periodic_2d_nearest_n() {
     let knns: Vec<Vec<(f64, &usize)>> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.nearest_periodic(&q, N, &squared_euclidean, p).unwrap()
+            t.nearest_periodic(q, N, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
     for (query_index, q) in query.iter().enumerate() {
         
         // Initialize neighbor
-        let mut neighbors = [(std::f64::MAX, std::usize::MAX); N];
+        let mut neighbors = [(f64::MAX, usize::MAX); N];
 
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -920,13 +1570,13 @@ fn test_periodic_2d_nearest_n() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -942,7 +1592,7 @@ fn test_periodic_2d_nearest_n() {
         // Check that brute force result agrees with KdTree result
         for (k, neighbor) in neighbors.iter().enumerate() {
             assert_eq!(&neighbor.1, knns[query_index][k].1, "{} {}", neighbor.0, knns[query_index][k].0);
-            assert!((neighbor.0 - knns[query_index][k].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
+            assert!((neighbor.0 - knns[query_index][k].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
         }
     }
 }
@@ -999,19 +1649,19 @@ fn test_periodic_3d_nearest_n() {
     let knns: Vec<Vec<(f64, &usize)>> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.nearest_periodic(&q, N, &squared_euclidean, p).unwrap()
+            t.nearest_periodic(q, N, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
     for (query_index, q) in query.iter().enumerate() {
         
         // Initialize neighbor
-        let mut neighbors = [(std::f64::MAX, std::usize::MAX); N];
+        let mut neighbors = [(f64::MAX, usize::MAX); N];
 
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -1025,13 +1675,13 @@ fn test_periodic_3d_nearest_n() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -1047,7 +1697,7 @@ fn test_periodic_3d_nearest_n() {
         // Check that brute force result agrees with KdTree result
         for (k, neighbor) in neighbors.iter().enumerate() {
             assert_eq!(&neighbor.1, knns[query_index][k].1, "{} {}", neighbor.0, knns[query_index][k].0);
-            assert!((neighbor.0 - knns[query_index][k].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
+            assert!((neighbor.0 - knns[query_index][k].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
         }
 
     }
@@ -1106,7 +1756,7 @@ fn test_periodic_1d_within() {
     let knns: Vec<Vec<(f64, &usize)>> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.within_periodic(&q, RADIUS, &squared_euclidean, p).unwrap()
+            t.within_periodic(q, RADIUS, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
@@ -1118,7 +1768,7 @@ fn test_periodic_1d_within() {
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -1132,13 +1782,13 @@ fn test_periodic_1d_within() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -1156,7 +1806,7 @@ fn test_periodic_1d_within() {
         // Check that brute force result agrees with KdTree result
         for (k, neighbor) in neighbors.iter().enumerate() {
             assert_eq!(&neighbor.1, knns[query_index][k].1, "{} {}", neighbor.0, knns[query_index][k].0);
-            assert!((neighbor.0 - knns[query_index][k].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
+            assert!((neighbor.0 - knns[query_index][k].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
         }
     }
 }
@@ -1213,7 +1863,7 @@ fn test_periodic_2d_within() {
     let knns: Vec<Vec<(f64, &usize)>> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.within_periodic(&q, RADIUS, &squared_euclidean, p).unwrap()
+            t.within_periodic(q, RADIUS, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
@@ -1225,7 +1875,7 @@ fn test_periodic_2d_within() {
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -1239,13 +1889,13 @@ fn test_periodic_2d_within() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -1263,7 +1913,7 @@ fn test_periodic_2d_within() {
         // Check that brute force result agrees with KdTree result
         for (k, neighbor) in neighbors.iter().enumerate() {
             assert_eq!(&neighbor.1, knns[query_index][k].1, "{} {}", neighbor.0, knns[query_index][k].0);
-            assert!((neighbor.0 - knns[query_index][k].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
+            assert!((neighbor.0 - knns[query_index][k].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
         }
     }
 }
@@ -1320,7 +1970,7 @@ fn test_periodic_3d_within() {
     let knns: Vec<Vec<(f64, &usize)>> = (&query)
         .into_par_iter()
         .map_with((&tree, &PERIODIC), |(t, p), q| {
-            t.within_periodic(&q, RADIUS, &squared_euclidean, p).unwrap()
+            t.within_periodic(q, RADIUS, &squared_euclidean, p).unwrap()
         }).collect::<Vec<_>>();
 
     // Check vs brute force
@@ -1332,7 +1982,7 @@ fn test_periodic_3d_within() {
         for (data_index, d) in data.iter().enumerate() {
 
             // Initialize min
-            let mut min: f64 = std::f64::MAX;
+            let mut min: f64 = f64::MAX;
 
             // Calculate distance for every image lazily (i.e. 3^K instead of 2^K)
             for image_idx in 0..3_i32.pow(K as u32) {
@@ -1346,13 +1996,13 @@ fn test_periodic_3d_within() {
                 }
 
                 // Construct current image position
-                let mut image: [f64; K] = q.clone();
+                let mut image: [f64; K] = *q;
                 for idx in 0..K {
                     image[idx] += (current_image[idx] as f64)*PERIODIC[idx];
                 }
 
                 // Calculate distance for this image
-                let image_distance = squared_euclidean(&image, &d);
+                let image_distance = squared_euclidean(&image, d);
 
                 // Compare with current min
                 min = min.min(image_distance);
@@ -1370,7 +2020,7 @@ fn test_periodic_3d_within() {
         // Check that brute force result agrees with KdTree result
         for (k, neighbor) in neighbors.iter().enumerate() {
             assert_eq!(&neighbor.1, knns[query_index][k].1, "{} {}", neighbor.0, knns[query_index][k].0);
-            assert!((neighbor.0 - knns[query_index][k].0).abs() < std::f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
+            assert!((neighbor.0 - knns[query_index][k].0).abs() < f64::EPSILON, "{} {}", neighbor.0, knns[query_index][k].0);
         }
 
     }
@@ -1388,7 +2038,25 @@ where
     slice.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
     array
-=======
+}
+
+// Returns a generator of points drawn uniformly from the unit box, seeded so that any
+// failure can be reproduced
+fn random_points<const K: usize>(seed: u64) -> impl FnMut() -> [f64; K] {
+    use rand::distributions::{Distribution, Uniform};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let uniform = Uniform::new(0.0, 1.0);
+    let mut rng = StdRng::seed_from_u64(seed);
+    move || {
+        let mut point = [0.0; K];
+        point.iter_mut().for_each(|x| *x = uniform.sample(&mut rng));
+        point
+    }
+}
+
+#[test]
 fn test_periodic_squared_euclidean_frontback_3d_f64(){
 
     // x-axis
@@ -1622,4 +2290,341 @@ fn test_periodic_kdtree_2d() {
             .unwrap()
             .collect::<Vec<_>>(),
         vec![(0f64, &0), (9f64, &1), (13f64, &3), (20f64, &2)]
-    );
\ No newline at end of file
+    );
+}
+
+#[test]
+fn periodic_box_validates_each_coordinate() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::new_periodic(F64_BOXSIZE_2D);
+
+    assert_eq!(kdtree.add(&[5.0, 5.0], 0), Ok(()));
+    assert_eq!(kdtree.add(&[0.0, 10.0], 1), Ok(()));
+    assert_eq!(
+        kdtree.add(&[11.0, 5.0], 2),
+        Err(ErrorKind::PeriodicOutOfBounds {
+            operation: Operation::Add,
+            axis: 0,
+            value: 11.0,
+            lower: 0.0,
+            upper: 10.0
+        })
+    );
+    assert_eq!(
+        kdtree.add(&[5.0, -1.0], 3).map_err(|err| err.axis()),
+        Err(Some(1))
+    );
+    assert_eq!(
+        kdtree
+            .nearest(&[5.0, 10.5], 1, &squared_euclidean)
+            .map_err(|err| err.operation()),
+        Err(Some(Operation::Query))
+    );
+    assert_eq!(
+        kdtree
+            .remove(&[5.0, 10.5], &0)
+            .map_err(|err| err.operation()),
+        Err(Some(Operation::Remove))
+    );
+
+    let mut kdtree: KdTree<f64, usize, 2> =
+        KdTree::new_periodic(PeriodicBox::new([-5.0, -5.0], [5.0, 5.0]).with_axes([true, false]));
+
+    assert_eq!(kdtree.add(&[-4.0, 100.0], 0), Ok(()));
+    assert_eq!(
+        kdtree.add(&[-6.0, 0.0], 1),
+        Err(ErrorKind::PeriodicOutOfBounds {
+            operation: Operation::Add,
+            axis: 0,
+            value: -6.0,
+            lower: -5.0,
+            upper: 5.0
+        })
+    );
+    assert_eq!(kdtree.size(), 1);
+
+    assert_eq!(
+        KdTree::<f64, usize, 2>::periodic_with_per_node_capacity(16, [10.0, 0.0]).err(),
+        Some(ErrorKind::InvalidPeriodicBox {
+            axis: 1,
+            lower: 0.0,
+            upper: 0.0
+        })
+    );
+    assert_eq!(
+        KdTree::<f64, usize, 2>::periodic_with_per_node_capacity(16, [10.0, f64::INFINITY])
+            .map_err(|err| err.axis())
+            .err(),
+        Some(Some(1))
+    );
+}
+
+#[test]
+fn periodic_box_wraps_points_on_insert() {
+    let periodic = PeriodicBox::new([-5.0, 0.0], [5.0, 10.0]).with_wrapping(true);
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::new_periodic(periodic);
+
+    kdtree.add(&[6.0, -3.0], 0).unwrap();
+    kdtree.add(&[-25.0, 33.0], 1).unwrap();
+
+    let nearest = kdtree
+        .nearest_with_point(&[-4.0, 7.0], 2, &squared_euclidean)
+        .unwrap();
+    assert_eq!(nearest[0].1, &[-4.0, 7.0]);
+    assert_eq!(*nearest[0].2, 0);
+    assert_eq!(nearest[1].1, &[-5.0, 3.0]);
+    assert_eq!(*nearest[1].2, 1);
+
+    assert_eq!(kdtree.remove(&[16.0, 17.0], &0), Ok(1));
+    assert_eq!(kdtree.size(), 1);
+}
+
+#[test]
+fn periodic_queries_match_brute_force_in_high_dimensions() {
+    const K: usize = 8;
+    const PERIODIC: [f64; K] = [1.0; K];
+
+    let mut random_point = random_points::<K>(35);
+
+    let mut tree: KdTree<f64, usize, K> = KdTree::new();
+    let data: Vec<[f64; K]> = (0..2_000).map(|_| random_point()).collect();
+    for (idx, point) in data.iter().enumerate() {
+        tree.add(point, idx).unwrap();
+    }
+
+    for _ in 0..100 {
+        let query = random_point();
+        let mut expected: Vec<f64> = data
+            .iter()
+            .map(|p| kiddo::kiddo::get_distance(&query, p, &squared_euclidean, Some(PERIODIC)))
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let nearest = tree
+            .nearest_periodic(&query, 5, &squared_euclidean, &PERIODIC)
+            .unwrap();
+        assert_eq!(nearest.len(), 5);
+        for ((dist, _), expected) in nearest.iter().zip(expected.iter()) {
+            assert!((dist - expected).abs() < F64_TOLERANCE);
+        }
+
+        let nearest_one = tree
+            .nearest_one_periodic(&query, &squared_euclidean, &PERIODIC)
+            .unwrap();
+        assert!((nearest_one.0 - expected[0]).abs() < F64_TOLERANCE);
+
+        let radius = (expected[20] + expected[21]) / 2.0;
+        let within = tree
+            .within_periodic(&query, radius, &squared_euclidean, &PERIODIC)
+            .unwrap();
+        assert_eq!(within.len(), 21);
+        for ((dist, _), expected) in within.iter().zip(expected.iter()) {
+            assert!((dist - expected).abs() < F64_TOLERANCE);
+        }
+    }
+}
+
+#[test]
+fn periodic_queries_honour_axis_mask() {
+    let periodic =
+        PeriodicBox::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]).with_axes([true, false, true]);
+
+    let mut random_point = random_points::<3>(36);
+
+    // along the open axis, the shortest path never crosses the boundary
+    let brute_force_distance = |a: &[f64; 3], b: &[f64; 3]| -> f64 {
+        (0..3)
+            .map(|idx| {
+                let delta = (a[idx] - b[idx]).abs();
+                let delta = if periodic.axes[idx] {
+                    delta.min(1.0 - delta)
+                } else {
+                    delta
+                };
+                delta * delta
+            })
+            .sum()
+    };
+
+    let mut tree: KdTree<f64, usize, 3> = KdTree::new();
+    let data: Vec<[f64; 3]> = (0..1_000).map(|_| random_point()).collect();
+    for (idx, point) in data.iter().enumerate() {
+        tree.add(point, idx).unwrap();
+    }
+
+    for _ in 0..100 {
+        let query = random_point();
+        let mut expected: Vec<f64> = data
+            .iter()
+            .map(|p| brute_force_distance(&query, p))
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let nearest = tree
+            .nearest_periodic(&query, 5, &squared_euclidean, &periodic)
+            .unwrap();
+        for ((dist, _), expected) in nearest.iter().zip(expected.iter()) {
+            assert!((dist - expected).abs() < F64_TOLERANCE);
+        }
+
+        let nearest_one = tree
+            .nearest_one_periodic(&query, &squared_euclidean, &periodic)
+            .unwrap();
+        assert!((nearest_one.0 - expected[0]).abs() < F64_TOLERANCE);
+
+        let radius = (expected[10] + expected[11]) / 2.0;
+        let within = tree
+            .within_unsorted_periodic(&query, radius, &squared_euclidean, &periodic)
+            .unwrap();
+        assert_eq!(within.len(), 11);
+    }
+
+    assert_eq!(
+        tree.nearest_periodic(&[0.5, 0.5, 1.5], 1, &squared_euclidean, &periodic),
+        Err(ErrorKind::PeriodicOutOfBounds {
+            operation: Operation::Query,
+            axis: 2,
+            value: 1.5,
+            lower: 0.0,
+            upper: 1.0
+        })
+    );
+    assert!(tree
+        .nearest_one_periodic(&[0.5, 1.5, 0.5], &squared_euclidean, &periodic)
+        .is_ok());
+    assert_eq!(
+        tree.within_periodic(&[0.5, 0.5, 0.5], 0.1, &squared_euclidean, &[1.0, 0.0, 1.0]),
+        Err(ErrorKind::InvalidPeriodicBox {
+            axis: 1,
+            lower: 0.0,
+            upper: 0.0
+        })
+    );
+}
+
+#[test]
+fn periodic_methods_override_the_trees_box() {
+    const PERIODIC: [f64; 2] = [20.0, 20.0];
+
+    let mut tree: KdTree<f64, usize, 2> = KdTree::new_periodic([10.0, 10.0]);
+    tree.add(&[1.0, 1.0], 0).unwrap();
+    tree.add(&[9.0, 1.0], 1).unwrap();
+
+    // the query lies outside the tree's own box, but inside the one given for the query
+    let query = [16.0, 1.0];
+    assert_eq!(
+        tree.nearest_periodic(&query, 2, &squared_euclidean, &PERIODIC),
+        Ok(vec![(25.0, &0), (49.0, &1)])
+    );
+    assert_eq!(
+        tree.nearest_one_periodic(&query, &squared_euclidean, &PERIODIC),
+        Ok((25.0, &0))
+    );
+    assert_eq!(
+        tree.within_periodic(&query, 30.0, &squared_euclidean, &PERIODIC),
+        Ok(vec![(25.0, &0)])
+    );
+    assert_eq!(
+        tree.within_unsorted_periodic(&query, 30.0, &squared_euclidean, &PERIODIC),
+        Ok(vec![(25.0, &0)])
+    );
+    assert_eq!(
+        tree.best_n_within_periodic(&query, 50.0, 1, &squared_euclidean, &PERIODIC),
+        Ok(vec![0])
+    );
+    let mut iter = tree
+        .iter_nearest_periodic(&query, &squared_euclidean, &PERIODIC)
+        .unwrap();
+    assert_eq!(iter.next(), Some((25.0, &0)));
+
+    assert_eq!(
+        tree.nearest_one_periodic(&[25.0, 1.0], &squared_euclidean, &PERIODIC),
+        Err(ErrorKind::PeriodicOutOfBounds {
+            operation: Operation::Query,
+            axis: 0,
+            value: 25.0,
+            lower: 0.0,
+            upper: 20.0
+        })
+    );
+}
+
+#[test]
+fn periodic_tree_queries_match_periodic_methods() {
+    let periodic =
+        PeriodicBox::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]).with_axes([true, true, false]);
+
+    let mut random_point = random_points::<3>(37);
+
+    let mut open_tree: KdTree<f64, usize, 3> = KdTree::new();
+    let mut periodic_tree: KdTree<f64, usize, 3> = KdTree::new_periodic(periodic);
+    for idx in 0..500 {
+        let point = random_point();
+        open_tree.add(&point, idx).unwrap();
+        periodic_tree.add(&point, idx).unwrap();
+    }
+
+    let mut searcher = periodic_tree.searcher();
+    for _ in 0..50 {
+        let query = random_point();
+        let expected = open_tree
+            .nearest_periodic(&query, 10, &squared_euclidean, &periodic)
+            .unwrap();
+        let radius = expected[9].0;
+
+        assert_eq!(
+            periodic_tree
+                .nearest(&query, 10, &squared_euclidean)
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            searcher.nearest(&query, 10, &squared_euclidean).unwrap(),
+            expected
+        );
+        assert_eq!(
+            periodic_tree
+                .nearest_one(&query, &squared_euclidean)
+                .unwrap(),
+            expected[0]
+        );
+        assert_eq!(
+            periodic_tree
+                .within(&query, radius, &squared_euclidean)
+                .unwrap(),
+            open_tree
+                .within_periodic(&query, radius, &squared_euclidean, &periodic)
+                .unwrap()
+        );
+        assert_eq!(
+            periodic_tree
+                .iter_nearest(&query, &squared_euclidean)
+                .unwrap()
+                .take(10)
+                .collect::<Vec<_>>(),
+            open_tree
+                .iter_nearest_periodic(&query, &squared_euclidean, &periodic)
+                .unwrap()
+                .take(10)
+                .collect::<Vec<_>>()
+        );
+
+        let mut best = periodic_tree
+            .best_n_within(&query, radius, 3, &squared_euclidean)
+            .unwrap();
+        let mut expected_best = open_tree
+            .best_n_within_periodic(&query, radius, 3, &squared_euclidean, &periodic)
+            .unwrap();
+        best.sort_unstable();
+        expected_best.sort_unstable();
+        assert_eq!(best, expected_best);
+
+        // overriding the tree's box with one that never wraps gives the open results
+        let unwrapped = periodic.with_axes([false; 3]);
+        assert_eq!(
+            periodic_tree
+                .nearest_periodic(&query, 10, &squared_euclidean, &unwrapped)
+                .unwrap(),
+            open_tree.nearest(&query, 10, &squared_euclidean).unwrap()
+        );
+    }
+}
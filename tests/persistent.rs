@@ -0,0 +1,132 @@
+#![cfg(feature = "alloc")]
+
+extern crate kiddo;
+
+use kiddo::distance::squared_euclidean;
+use kiddo::ErrorKind;
+use kiddo::KdTree;
+use kiddo::PeriodicBox;
+use kiddo::PersistentKdTree;
+
+fn point(idx: usize) -> [f64; 2] {
+    [(idx * 37 % 101) as f64, (idx * 13 % 29) as f64]
+}
+
+fn sorted_items(results: Vec<(f64, &usize)>) -> Vec<usize> {
+    let mut items: Vec<usize> = results.into_iter().map(|(_, &item)| item).collect();
+    items.sort_unstable();
+    items
+}
+
+#[test]
+fn queries_match_kdtree() {
+    let mut persistent: PersistentKdTree<f64, usize, 2> =
+        PersistentKdTree::with_per_node_capacity(4).unwrap();
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    assert_eq!(
+        persistent.nearest_one(&[0.0, 0.0], &squared_euclidean),
+        Err(ErrorKind::Empty)
+    );
+
+    for idx in 0..500 {
+        persistent.add(&point(idx), idx).unwrap();
+        kdtree.add(&point(idx), idx).unwrap();
+    }
+    for idx in (0..500).step_by(3) {
+        assert_eq!(persistent.remove(&point(idx), &idx).unwrap(), 1);
+        kdtree.remove(&point(idx), &idx).unwrap();
+    }
+    assert_eq!(persistent.remove(&point(0), &0).unwrap(), 0);
+    assert_eq!(persistent.size(), kdtree.size());
+    let mut items: Vec<usize> = persistent.iter().map(|(_, &item)| item).collect();
+    let mut expected: Vec<usize> = kdtree.iter().map(|(_, &item)| item).collect();
+    items.sort_unstable();
+    expected.sort_unstable();
+    assert_eq!(items, expected);
+
+    for query in [[0.0, 0.0], [50.5, 3.2], [99.0, 28.0], [12.0, -4.0]] {
+        let dists = |results: Vec<(f64, &usize)>| -> Vec<f64> {
+            results.into_iter().map(|(dist, _)| dist).collect()
+        };
+        assert_eq!(
+            dists(persistent.nearest(&query, 10, &squared_euclidean).unwrap()),
+            dists(kdtree.nearest(&query, 10, &squared_euclidean).unwrap())
+        );
+        assert_eq!(
+            persistent
+                .nearest_one(&query, &squared_euclidean)
+                .unwrap()
+                .0,
+            kdtree.nearest_one(&query, &squared_euclidean).unwrap().0
+        );
+        assert_eq!(
+            sorted_items(persistent.within(&query, 50.0, &squared_euclidean).unwrap()),
+            sorted_items(kdtree.within(&query, 50.0, &squared_euclidean).unwrap())
+        );
+    }
+
+    assert!(matches!(
+        persistent.add(&[f64::NAN, 0.0], 0),
+        Err(ErrorKind::NonFiniteCoordinate { axis: 0, .. })
+    ));
+}
+
+#[test]
+fn snapshots_are_unaffected_by_later_modifications() {
+    let mut tree: PersistentKdTree<f64, usize, 2> =
+        PersistentKdTree::with_per_node_capacity(2).unwrap();
+    let mut snapshots = vec![];
+
+    for idx in 0..200 {
+        tree.add(&point(idx), idx).unwrap();
+        if idx % 50 == 49 {
+            snapshots.push(tree.snapshot());
+        }
+    }
+    for idx in 0..100 {
+        tree.remove(&point(idx), &idx).unwrap();
+    }
+
+    for (n, snapshot) in snapshots.iter().enumerate() {
+        let expected: Vec<usize> = (0..(n + 1) * 50).collect();
+        assert_eq!(snapshot.size(), expected.len());
+        assert_eq!(
+            sorted_items(
+                snapshot
+                    .within(&[50.0, 14.0], 1e6, &squared_euclidean)
+                    .unwrap()
+            ),
+            expected
+        );
+    }
+    assert_eq!(
+        sorted_items(tree.within(&[50.0, 14.0], 1e6, &squared_euclidean).unwrap()),
+        (100..200).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn periodic_tree_wraps_and_validates_points() {
+    let periodic = PeriodicBox::new([0.0, 0.0], [10.0, 10.0]);
+    let mut tree: PersistentKdTree<f64, usize, 2> =
+        PersistentKdTree::periodic_with_per_node_capacity(2, periodic).unwrap();
+    assert!(matches!(
+        tree.add(&[11.0, 5.0], 0),
+        Err(ErrorKind::PeriodicOutOfBounds { axis: 0, .. })
+    ));
+
+    let mut tree: PersistentKdTree<f64, usize, 2> =
+        PersistentKdTree::periodic_with_per_node_capacity(2, periodic.with_wrapping(true)).unwrap();
+    for idx in 0..20 {
+        tree.add(&[idx as f64 + 0.5, 5.0], idx).unwrap();
+    }
+    assert_eq!(tree.size(), 20);
+    assert_eq!(tree.remove(&[10.5, 5.0], &10).unwrap(), 1);
+
+    // 9.5 is held twice, while only one of the two elements at 0.5 remains
+    let nearest = tree.nearest(&[9.9, 5.0], 4, &squared_euclidean).unwrap();
+    let expected = [0.16, 0.16, 0.36, 1.96];
+    for ((dist, _), expected) in nearest.into_iter().zip(expected.iter()) {
+        assert!((dist - expected).abs() < 1e-9);
+    }
+}
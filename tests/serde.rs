@@ -1,5 +1,4 @@
-#[cfg(feature = "serialize")]
-use serde_json;
+#![cfg(feature = "serialize")]
 
 extern crate kiddo;
 
@@ -11,7 +10,6 @@ static POINT_B: ([f64; 2], usize) = ([1f64, 1f64], 1);
 static POINT_C: ([f64; 2], usize) = ([2f64, 2f64], 2);
 static POINT_D: ([f64; 2], usize) = ([3f64, 3f64], 3);
 
-#[cfg(feature = "serialize")]
 #[test]
 fn it_serializes_and_deserializes_properly() {
     let capacity_per_node = 2;
@@ -36,3 +34,130 @@ fn it_serializes_and_deserializes_properly() {
         vec![]
     );
 }
+
+#[test]
+fn it_round_trips_trees_as_a_flat_list_of_elements() {
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    for idx in 0..200 {
+        kdtree
+            .add(&[(idx * 37 % 101) as f64, (idx * 13 % 29) as f64], idx)
+            .unwrap();
+    }
+
+    let serialized = serde_json::to_value(&kdtree).unwrap();
+    assert_eq!(serialized["version"], 1);
+    assert_eq!(serialized["dimensions"], 2);
+    assert_eq!(serialized["capacity"], 4);
+    assert_eq!(serialized["points"].as_array().unwrap().len(), 400);
+    assert_eq!(serialized["items"].as_array().unwrap().len(), 200);
+
+    let deserialized: KdTree<f64, usize, 2> = serde_json::from_value(serialized).unwrap();
+    assert_eq!(deserialized.size(), kdtree.size());
+    for query in [[0.0, 0.0], [50.5, 3.2], [99.0, 28.0]] {
+        let dists = |results: Vec<(f64, &usize)>| -> Vec<f64> {
+            results.into_iter().map(|(dist, _)| dist).collect()
+        };
+        assert_eq!(
+            dists(
+                deserialized
+                    .nearest(&query, 10, &squared_euclidean)
+                    .unwrap()
+            ),
+            dists(kdtree.nearest(&query, 10, &squared_euclidean).unwrap())
+        );
+    }
+}
+
+#[test]
+fn it_round_trips_deep_and_periodic_trees() {
+    // Each element lies halfway between the previous one and the origin, so adding them
+    // one at a time builds a tree hundreds of nodes deep
+    let mut deep: KdTree<f64, usize, 1> = KdTree::with_per_node_capacity(1).unwrap();
+    for idx in 0..500 {
+        deep.add(&[0.5f64.powi(idx as i32)], idx).unwrap();
+    }
+    assert!(deep.stats().max_depth > 400);
+
+    let serialized = serde_json::to_string(&deep).unwrap();
+    let deserialized: KdTree<f64, usize, 1> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.size(), 500);
+    assert_eq!(
+        *deserialized
+            .nearest_one(&[1.0], &squared_euclidean)
+            .unwrap()
+            .1,
+        0
+    );
+
+    let mut periodic: KdTree<f64, usize, 2> = KdTree::new_periodic([10.0, 10.0]);
+    periodic.add(&[0.5, 0.5], 0).unwrap();
+    periodic.add(&[5.0, 5.0], 1).unwrap();
+
+    let serialized = serde_json::to_string(&periodic).unwrap();
+    let deserialized: KdTree<f64, usize, 2> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.periodic(), periodic.periodic());
+    assert_eq!(
+        deserialized
+            .nearest_one(&[9.5, 9.5], &squared_euclidean)
+            .unwrap(),
+        (2.0, &0)
+    );
+}
+
+#[test]
+fn it_rejects_invalid_serialized_trees() {
+    let parse = |json: &str| serde_json::from_str::<KdTree<f64, usize, 2>>(json);
+    let valid = r#"{"version":1,"dimensions":2,"capacity":4,"periodic":null,
+        "points":[0.0,0.0,1.0,1.0],"items":[0,1]}"#;
+    assert_eq!(parse(valid).unwrap().size(), 2);
+
+    let version = parse(&valid.replace(r#""version":1"#, r#""version":2"#)).unwrap_err();
+    assert!(version
+        .to_string()
+        .contains("unsupported KdTree format version 2"));
+
+    let dimensions = parse(&valid.replace(r#""dimensions":2"#, r#""dimensions":3"#));
+    assert!(dimensions.is_err());
+
+    let capacity = parse(&valid.replace(r#""capacity":4"#, r#""capacity":0"#)).unwrap_err();
+    assert!(capacity.to_string().contains("zero capacity"));
+
+    assert!(parse(&valid.replace(r#""items":[0,1]"#, r#""items":[0]"#)).is_err());
+    assert!(parse(&valid.replace("1.0,1.0]", "1.0]")).is_err());
+}
+
+#[test]
+fn it_reads_the_format_version_before_the_rest_of_the_tree() {
+    let parse = |json: &str| serde_json::from_str::<KdTree<f64, usize, 2>>(json);
+
+    // a later version whose other fields differ is still reported by its version
+    let renamed = parse(r#"{"version":2,"nodes":{"leaf":[]}}"#).unwrap_err();
+    assert!(renamed
+        .to_string()
+        .contains("unsupported KdTree format version 2"));
+    let retyped = parse(r#"[2,"two",{}]"#).unwrap_err();
+    assert!(retyped
+        .to_string()
+        .contains("unsupported KdTree format version 2"));
+
+    let seq = parse(r#"[1,2,4,null,[0.0,0.0,1.0,1.0],[0,1]]"#).unwrap();
+    assert_eq!(seq.size(), 2);
+
+    // trees written by earlier releases hold their nodes, and begin with their size
+    let unversioned = parse(
+        r#"{"size":1,"min_bounds":[0.0,0.0],"max_bounds":[0.0,0.0],
+        "content":{"Leaf":{"points":[0.0,0.0],"bucket":[0],"capacity":16}}}"#,
+    )
+    .unwrap_err();
+    assert!(unversioned.to_string().contains("no format version"));
+
+    let zero_dimensions: KdTree<f64, usize, 0> = serde_json::from_str(
+        r#"{"version":1,"dimensions":0,"capacity":4,"periodic":null,"points":[],"items":[]}"#,
+    )
+    .unwrap();
+    assert_eq!(zero_dimensions.size(), 0);
+    assert!(serde_json::from_str::<KdTree<f64, usize, 0>>(
+        r#"{"version":1,"dimensions":0,"capacity":4,"periodic":null,"points":[0.0],"items":[]}"#
+    )
+    .is_err());
+}
@@ -0,0 +1,165 @@
+#![cfg(all(feature = "heapless", feature = "alloc"))]
+
+extern crate kiddo;
+
+use kiddo::distance::squared_euclidean;
+use kiddo::heapless;
+use kiddo::ErrorKind;
+use kiddo::KdTree;
+use kiddo::StaticKdTree;
+
+fn point(idx: usize) -> [f64; 2] {
+    [(idx * 37 % 101) as f64, (idx * 13 % 29) as f64]
+}
+
+#[test]
+fn queries_match_kdtree() {
+    let mut fixed: StaticKdTree<f64, usize, 2, 500, 4> = StaticKdTree::new();
+    let mut kdtree: KdTree<f64, usize, 2> = KdTree::with_per_node_capacity(4).unwrap();
+    assert_eq!(
+        fixed.nearest_one(&[0.0, 0.0], &squared_euclidean),
+        Err(ErrorKind::Empty)
+    );
+
+    for idx in 0..500 {
+        fixed.add(&point(idx), idx).unwrap();
+        kdtree.add(&point(idx), idx).unwrap();
+    }
+    assert_eq!(fixed.size(), 500);
+    assert_eq!(
+        fixed.add(&[0.5, 0.5], 500),
+        Err(ErrorKind::CapacityExceeded)
+    );
+    assert_eq!(fixed.size(), 500);
+
+    for query in [[0.0, 0.0], [50.5, 3.2], [99.0, 28.0], [12.0, -4.0]] {
+        let mut results = [None; 10];
+        assert_eq!(
+            fixed
+                .nearest(&query, &mut results, &squared_euclidean)
+                .unwrap(),
+            10
+        );
+        let dists: Vec<f64> = results.iter().map(|result| result.unwrap().0).collect();
+        let expected: Vec<f64> = kdtree
+            .nearest(&query, 10, &squared_euclidean)
+            .unwrap()
+            .into_iter()
+            .map(|(dist, _)| dist)
+            .collect();
+        assert_eq!(dists, expected);
+
+        assert_eq!(
+            fixed.nearest_one(&query, &squared_euclidean).unwrap().0,
+            kdtree.nearest_one(&query, &squared_euclidean).unwrap().0
+        );
+
+        let within: heapless::Vec<_, 64> = fixed.within(&query, 50.0, &squared_euclidean).unwrap();
+        let mut items: Vec<usize> = within.iter().map(|&(_, &item)| item).collect();
+        let mut expected: Vec<usize> = kdtree
+            .within(&query, 50.0, &squared_euclidean)
+            .unwrap()
+            .into_iter()
+            .map(|(_, &item)| item)
+            .collect();
+        items.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(items, expected);
+    }
+}
+
+#[test]
+fn small_queries_and_capacity_errors() {
+    let mut tree: StaticKdTree<f64, usize, 2, 16, 2> = StaticKdTree::new();
+    assert_eq!(tree.capacity(), 16);
+    assert_eq!(
+        tree.nearest(&[0.0, 0.0], &mut [None; 3], &squared_euclidean),
+        Ok(0)
+    );
+
+    assert!(matches!(
+        tree.add(&[f64::NAN, 0.0], 0),
+        Err(ErrorKind::NonFiniteCoordinate { axis: 0, .. })
+    ));
+    tree.add(&[1.0, 1.0], 0).unwrap();
+    tree.add(&[2.0, 2.0], 1).unwrap();
+
+    // A third element at the same point as two others cannot be split from them
+    tree.add(&[5.0, 5.0], 2).unwrap();
+    tree.add(&[5.0, 5.0], 3).unwrap();
+    assert_eq!(tree.add(&[5.0, 5.0], 4), Err(ErrorKind::CapacityExceeded));
+    assert_eq!(tree.size(), 4);
+
+    let mut results = [None; 3];
+    assert_eq!(
+        tree.nearest(&[0.0, 0.0], &mut results, &squared_euclidean),
+        Ok(3)
+    );
+    assert_eq!(
+        results,
+        [Some((2.0, &0)), Some((8.0, &1)), Some((50.0, &2))]
+    );
+
+    let too_small: Result<heapless::Vec<_, 1>, _> =
+        tree.within(&[5.0, 5.0], 1.0, &squared_euclidean);
+    assert_eq!(too_small, Err(ErrorKind::CapacityExceeded));
+
+    // Elements at no meaningful distance are only returned if no other element is found
+    let nan_at_first = |a: &[f64; 2], b: &[f64; 2]| {
+        if b == &[1.0, 1.0] {
+            f64::NAN
+        } else {
+            squared_euclidean(a, b)
+        }
+    };
+    assert_eq!(tree.nearest_one(&[1.0, 1.0], &nan_at_first), Ok((2.0, &1)));
+    let (dist, _) = tree.nearest_one(&[0.0, 0.0], &|_, _| f64::NAN).unwrap();
+    assert!(dist.is_nan());
+
+    assert!(matches!(
+        tree.nearest_one(&[0.0, f64::INFINITY], &squared_euclidean),
+        Err(ErrorKind::NonFiniteCoordinate { axis: 1, .. })
+    ));
+}
+
+#[test]
+fn footprint_does_not_depend_on_leaf_size() {
+    use core::mem::size_of;
+
+    assert_eq!(
+        size_of::<StaticKdTree<f64, usize, 3, 1000, 1>>(),
+        size_of::<StaticKdTree<f64, usize, 3, 1000, 64>>()
+    );
+
+    let mut tree: StaticKdTree<f64, usize, 2, 500, 64> = StaticKdTree::new();
+    for idx in 0..500 {
+        tree.add(&point(idx), idx).unwrap();
+    }
+    let within: heapless::Vec<_, 500> =
+        tree.within(&[50.0, 14.0], 1e6, &squared_euclidean).unwrap();
+    assert_eq!(within.len(), 500);
+}
+
+#[test]
+fn queries_search_unbalanced_trees() {
+    // Each point is half as far from the origin as the last, so every split leaves one of
+    // them on one side and the rest on the other, and the tree is as deep as it is large
+    let mut tree: StaticKdTree<f64, usize, 1, 60, 1> = StaticKdTree::new();
+    for idx in 0..60 {
+        tree.add(&[0.5f64.powi(idx as i32)], idx).unwrap();
+    }
+
+    assert_eq!(*tree.nearest_one(&[0.0], &squared_euclidean).unwrap().1, 59);
+    assert_eq!(*tree.nearest_one(&[2.0], &squared_euclidean).unwrap().1, 0);
+
+    let mut results = [None; 3];
+    assert_eq!(
+        tree.nearest(&[0.0], &mut results, &squared_euclidean),
+        Ok(3)
+    );
+    let items: Vec<usize> = results.iter().map(|result| *result.unwrap().1).collect();
+    assert_eq!(items, [59, 58, 57]);
+
+    let within: heapless::Vec<_, 60> = tree.within(&[0.0], 1.0, &squared_euclidean).unwrap();
+    assert_eq!(within.len(), 60);
+}